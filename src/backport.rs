@@ -0,0 +1,432 @@
+//! Backport a request's commits onto another branch by cherry-picking them
+use crate::remotes::{MergeRequest, NewRequest};
+use crate::{abort, get_remote_hard, get_request_id_or_current, git};
+use anyhow::{anyhow, Result};
+use colored::*;
+use log::{debug, info, trace};
+
+/// The project config section holding the backport in progress
+const STATE_SECTION: &str = "backport";
+
+/// The fields of the backport state, as saved in the project config
+const STATE_FIELDS: [&str; 6] = ["remote", "request", "onto", "branch", "commits", "create"];
+
+/// How a request should be backported
+#[derive(Debug)]
+pub struct BackportOptions {
+    /// The branch to pick the commits onto
+    pub onto: String,
+    /// Open a request for the backport once its commits are picked
+    pub create: bool,
+}
+
+/// A backport in progress, saved in the project config so that it can be continued after
+/// resolving conflicts
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BackportState {
+    remote_name: String,
+    mr_id: i64,
+    onto: String,
+    branch: String,
+    /// The commits left to pick, oldest first. When stopped, the first is the one that conflicted.
+    commits: Vec<String>,
+    create: bool,
+}
+
+impl BackportState {
+    /// The config values that make up the state, in the order of `STATE_FIELDS`
+    fn to_fields(&self) -> Vec<(&'static str, String)> {
+        let values = [
+            self.remote_name.clone(),
+            self.mr_id.to_string(),
+            self.onto.clone(),
+            self.branch.clone(),
+            self.commits.join(" "),
+            self.create.to_string(),
+        ];
+        STATE_FIELDS.into_iter().zip(values).collect()
+    }
+
+    /// Read the state from the config fields, or `None` if no backport is in progress
+    fn from_fields(get: impl Fn(&str) -> Option<String>) -> Result<Option<Self>> {
+        let Some(mr_id) = get("request") else {
+            return Ok(None);
+        };
+        let field = |name: &str| get(name).ok_or_else(|| anyhow!("'{}' is missing", name));
+        Ok(Some(BackportState {
+            remote_name: field("remote")?,
+            mr_id: mr_id
+                .parse()
+                .map_err(|_| anyhow!("'{}' isn't a request ID", mr_id))?,
+            onto: field("onto")?,
+            branch: field("branch")?,
+            commits: field("commits")?
+                .split_whitespace()
+                .map(String::from)
+                .collect(),
+            create: field("create")? == "true",
+        }))
+    }
+
+    /// Read the backport in progress from the project config
+    fn load() -> Result<Option<Self>> {
+        Self::from_fields(|name| git::get_project_config(&format!("{}.{}", STATE_SECTION, name)))
+    }
+
+    /// Write the state to the project config
+    fn save(&self) {
+        for (name, value) in self.to_fields() {
+            git::set_project_config(&format!("{}.{}", STATE_SECTION, name), &value);
+        }
+    }
+
+    /// Remove the state from the project config, including any partly saved fields
+    fn clear() {
+        for name in STATE_FIELDS {
+            let field = format!("{}.{}", STATE_SECTION, name);
+            if let Err(error) = git::delete_project_config(&field) {
+                trace!("Couldn't delete {}: {}", field, error);
+            }
+        }
+    }
+}
+
+/// The hidden refs that keep the request's commits reachable during the backport
+fn get_refs_prefix(mr_id: i64) -> String {
+    format!("backport/{}", mr_id)
+}
+
+/// Delete the fetched refs once the backport is over
+fn delete_fetched_refs(mr_id: i64) {
+    if let Err(error) = git::delete_hidden_refs(&get_refs_prefix(mr_id)) {
+        debug!("Couldn't clean up the fetched refs: {}", error);
+    }
+}
+
+/// The name of the branch that the commits are picked onto
+fn get_backport_branch_name(mr_id: i64, onto: &str) -> String {
+    format!("backport/{}-{}", mr_id, onto)
+}
+
+/// The title and description for the backport's own request, referring to the original
+fn describe_backport(mr: &MergeRequest, onto: &str) -> (String, String) {
+    (
+        format!("[{}] {}", onto, mr.title),
+        format!("Backport of {} onto `{}`.", mr.web_url, onto),
+    )
+}
+
+/// Determine if a merged request's commits landed as its merge commit alone: a true merge, or a
+/// squash of them. A request rebased onto its target instead has its last commit reported as the
+/// merge commit, which keeps that commit's message.
+fn is_merged_as_one(parent_count: usize, merge_message: &str, own_messages: &[String]) -> bool {
+    if parent_count > 1 || own_messages.len() < 2 {
+        return true;
+    }
+    own_messages
+        .last()
+        .is_some_and(|message| message.trim() != merge_message.trim())
+}
+
+/// Fetch the commits to pick: the merge or squashed commit once the request is merged that way,
+/// and the request's own commits otherwise
+fn fetch_commits(remote_name: &str, mr: &MergeRequest, head_ref: &str) -> Result<Vec<String>> {
+    let prefix = get_refs_prefix(mr.id);
+    let commits =
+        git::fetch_request_commits(remote_name, mr.id, &prefix, head_ref, &mr.target_branch)?;
+    let Some(sha) = &mr.merge_commit_sha else {
+        return Ok(commits);
+    };
+    debug!("Request {} was merged as {}", mr.id, sha);
+    let own_messages = commits
+        .iter()
+        .map(|commit| git::get_commit_message(commit))
+        .collect::<Result<Vec<_>>>()?;
+    let parent_count = git::get_parent_count(sha)?;
+    if is_merged_as_one(parent_count, &git::get_commit_message(sha)?, &own_messages) {
+        return Ok(vec![git::resolve_commit(sha)?]);
+    }
+    debug!(
+        "Request {} was rebased onto its target; picking its own commits",
+        mr.id
+    );
+    Ok(commits)
+}
+
+/// Explain how to carry on after a commit fails to pick, then exit
+fn stop_for_conflicts(error: &anyhow::Error) -> ! {
+    let message = format!(
+        "{}. Resolve the conflicts and stage them, then run 'git req backport --continue'. If the \
+         commit isn't needed, run 'git req backport --skip'. Run 'git req backport --abort' to \
+         give up.",
+        error
+    );
+    abort(&message);
+}
+
+/// Pick the remaining commits, saving the state after each one so that a conflict can be resumed
+/// from, then finish the backport
+fn pick_commits(mut state: BackportState) {
+    while let Some(sha) = state.commits.first() {
+        info!("Picking {}", sha);
+        if let Err(error) = git::cherry_pick(sha) {
+            stop_for_conflicts(&error);
+        }
+        state.commits.remove(0);
+        state.save();
+    }
+    finish_backport(&state);
+}
+
+/// Clear the saved state, then open a request for the backport if asked to
+fn finish_backport(state: &BackportState) {
+    BackportState::clear();
+    delete_fetched_refs(state.mr_id);
+    println!(
+        "Backported request {} onto {} as {}",
+        state.mr_id.to_string().green(),
+        state.onto,
+        state.branch.green()
+    );
+    if !state.create {
+        return;
+    }
+    let mut remote = get_remote_hard(&state.remote_name, true);
+    let mr = remote.get_req(state.mr_id).unwrap_or_else(|error| {
+        let message = format!("There was a problem querying the request: {}", error);
+        abort(&message);
+    });
+    git::push_branch(&state.remote_name, &state.branch).unwrap_or_else(|error| {
+        let message = format!("There was a problem pushing the branch: {}", error);
+        abort(&message);
+    });
+    let (title, description) = describe_backport(&mr, &state.onto);
+    let new_req = NewRequest {
+        title,
+        description,
+        source_branch: state.branch.clone(),
+        target_branch: state.onto.clone(),
+        draft: false,
+    };
+    let backport = remote.create_req(&new_req).unwrap_or_else(|error| {
+        let message = format!("There was a problem creating the request: {}", error);
+        abort(&message);
+    });
+    println!(
+        "Created request {}: {}",
+        backport.id.to_string().green(),
+        backport.web_url.underline()
+    );
+}
+
+/// Read the backport in progress, exiting if there isn't one
+fn load_state_hard() -> BackportState {
+    match BackportState::load() {
+        Ok(Some(state)) => state,
+        Ok(None) => abort("No backport is in progress"),
+        Err(error) => {
+            let message = format!(
+                "The backport in progress couldn't be read ({}); run 'git req backport --abort'",
+                error
+            );
+            abort(&message);
+        }
+    }
+}
+
+/// Start backporting the given request, or the one for the current branch, onto another branch
+pub fn backport_request(remote_name: &str, request_id: Option<String>, options: &BackportOptions) {
+    if !matches!(BackportState::load(), Ok(None)) {
+        abort("A backport is already in progress; run 'git req backport --continue' or '--abort'");
+    }
+    let mut remote = get_remote_hard(remote_name, true);
+    debug!("Found remote: {}", remote);
+    let mr_id = get_request_id_or_current(remote_name, remote.as_mut(), request_id);
+    info!("Backporting MR {} onto {}", mr_id, options.onto);
+    let mr = remote.get_req(mr_id).unwrap_or_else(|error| {
+        let message = format!("There was a problem querying the request: {}", error);
+        abort(&message);
+    });
+    let head_ref = remote.get_remote_req_head_ref(mr_id);
+    // Every exit from here on cleans up the request's fetched refs
+    let commits = fetch_commits(remote_name, &mr, &head_ref).unwrap_or_else(|error| {
+        delete_fetched_refs(mr_id);
+        let message = format!("There was a problem finding the commits: {}", error);
+        abort(&message);
+    });
+    if commits.is_empty() {
+        delete_fetched_refs(mr_id);
+        abort(&format!("Request {} has no commits to backport", mr_id));
+    }
+    let base = git::fetch_branch(remote_name, &options.onto).unwrap_or_else(|error| {
+        delete_fetched_refs(mr_id);
+        let message = format!("There was a problem fetching '{}': {}", options.onto, error);
+        abort(&message);
+    });
+    let branch = get_backport_branch_name(mr_id, &options.onto);
+    git::create_branch(&branch, &base).unwrap_or_else(|error| {
+        delete_fetched_refs(mr_id);
+        abort(&error.to_string());
+    });
+    let state = BackportState {
+        remote_name: String::from(remote_name),
+        mr_id,
+        onto: options.onto.clone(),
+        branch,
+        commits,
+        create: options.create,
+    };
+    state.save();
+    pick_commits(state);
+}
+
+/// Commit the resolved cherry-pick, then pick the rest of the backport's commits. A commit that
+/// failed to pick without starting a cherry-pick, such as over uncommitted changes, is retried.
+pub fn continue_backport() {
+    let mut state = load_state_hard();
+    let picked = if git::is_cherry_pick_in_progress() {
+        git::continue_cherry_pick().unwrap_or_else(|error| {
+            stop_for_conflicts(&error);
+        });
+        true
+    } else {
+        // The cherry-pick may have been committed through git instead
+        state
+            .commits
+            .first()
+            .is_some_and(|sha| git::is_head_picked_from(sha))
+    };
+    if picked {
+        state.commits.remove(0);
+        state.save();
+    }
+    pick_commits(state);
+}
+
+/// Leave out the commit that stopped the backport, then pick the rest of its commits
+pub fn skip_backport_commit() {
+    let mut state = load_state_hard();
+    if git::is_cherry_pick_in_progress() {
+        git::skip_cherry_pick().unwrap_or_else(|error| {
+            abort(&error.to_string());
+        });
+    }
+    if !state.commits.is_empty() {
+        let sha = state.commits.remove(0);
+        println!("Skipped {}", sha);
+        state.save();
+    }
+    pick_commits(state);
+}
+
+/// Give up on the backport in progress, leaving its branch as it is
+pub fn abort_backport() {
+    let state = BackportState::load();
+    if git::is_cherry_pick_in_progress() {
+        git::abort_cherry_pick().unwrap_or_else(|error| {
+            abort(&error.to_string());
+        });
+    }
+    match state {
+        Ok(Some(state)) => {
+            BackportState::clear();
+            delete_fetched_refs(state.mr_id);
+            println!(
+                "Stopped backporting request {}; {} is left as it was",
+                state.mr_id.to_string().green(),
+                state.branch
+            );
+        }
+        Ok(None) => abort("No backport is in progress"),
+        Err(error) => {
+            debug!("Clearing unreadable backport state: {}", error);
+            BackportState::clear();
+            println!("Stopped the backport");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn make_state() -> BackportState {
+        BackportState {
+            remote_name: String::from("origin"),
+            mr_id: 17,
+            onto: String::from("release-1.2"),
+            branch: get_backport_branch_name(17, "release-1.2"),
+            commits: vec![String::from("abc"), String::from("def")],
+            create: true,
+        }
+    }
+
+    fn to_map(state: &BackportState) -> HashMap<&'static str, String> {
+        state.to_fields().into_iter().collect()
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let state = make_state();
+        let fields = to_map(&state);
+        assert_eq!(
+            Some(state),
+            BackportState::from_fields(|name| fields.get(name).cloned()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_state_none_in_progress() {
+        assert_eq!(None, BackportState::from_fields(|_| None).unwrap());
+    }
+
+    #[test]
+    fn test_state_corrupt() {
+        let mut fields = to_map(&make_state());
+        fields.remove("onto");
+        assert!(BackportState::from_fields(|name| fields.get(name).cloned()).is_err());
+        fields.insert("onto", String::from("release-1.2"));
+        fields.insert("request", String::from("seventeen"));
+        assert!(BackportState::from_fields(|name| fields.get(name).cloned()).is_err());
+    }
+
+    #[test]
+    fn test_is_merged_as_one() {
+        let own_messages = vec![
+            String::from("Add the parser\n"),
+            String::from("Handle empty input\n"),
+        ];
+        // A merge commit
+        assert!(is_merged_as_one(2, "Merge pull request #17", &own_messages));
+        // A squash
+        assert!(is_merged_as_one(
+            1,
+            "Fix the crash (#17)\n\n* Add the parser\n* Handle empty input",
+            &own_messages
+        ));
+        // A rebase, whose last commit stands in for the merge commit
+        assert!(!is_merged_as_one(1, "Handle empty input", &own_messages));
+        // A single commit can be picked however it landed
+        assert!(is_merged_as_one(1, "Add the parser", &own_messages[..1]));
+    }
+
+    #[test]
+    fn test_describe_backport() {
+        let mr = MergeRequest {
+            id: 17,
+            title: String::from("Fix the crash"),
+            web_url: String::from("https://github.com/arusahni/git-req/pull/17"),
+            ..MergeRequest::default()
+        };
+        assert_eq!(
+            (
+                String::from("[release-1.2] Fix the crash"),
+                String::from(
+                    "Backport of https://github.com/arusahni/git-req/pull/17 onto `release-1.2`."
+                )
+            ),
+            describe_backport(&mr, "release-1.2")
+        );
+    }
+}
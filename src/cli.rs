@@ -1,18 +1,380 @@
-use clap::{arg, Parser};
+use clap::{Parser, Subcommand, ValueEnum};
 use clap_complete::Shell;
 
+/// Fields that requests can be sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortKey {
+    Created,
+    Updated,
+    Id,
+    Title,
+}
+
+/// The direction to sort requests in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+/// Fields that requests can be grouped by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    Target,
+    Author,
+    Label,
+}
+
+/// How a request's commits are brought into its target branch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MergeMethod {
+    Merge,
+    Squash,
+    Rebase,
+}
+
+/// Actions on a single request that don't involve checking it out
+#[derive(Debug, Subcommand)]
+pub enum Commands {
+    /// Show a request's details without checking it out
+    Show {
+        #[arg(help = "The ID of the MR or PR, or '-' to reference the one previously checked out")]
+        request_id: String,
+
+        #[arg(long, help = "Print the details as JSON")]
+        json: bool,
+    },
+
+    /// Show the changes a request makes to its target branch without checking it out
+    Diff {
+        #[arg(help = "The ID of the MR or PR, or '-' to reference the one previously checked out")]
+        request_id: String,
+
+        #[arg(long, help = "Only show a summary of the changed files")]
+        stat: bool,
+    },
+
+    /// Compare the latest revision of a force-pushed request to the one seen before it
+    Interdiff {
+        #[arg(help = "The ID of the MR or PR, or '-' to reference the one previously checked out")]
+        request_id: String,
+
+        #[arg(
+            long,
+            help = "Compare the two latest versions recorded by the remote instead (GitLab only)"
+        )]
+        server: bool,
+    },
+
+    /// Show the open requests for the current branch
+    #[command(visible_alias = "current")]
+    Status,
+
+    /// Find the requests that contain a commit
+    ForCommit {
+        #[arg(
+            help = "The commit to look up",
+            required_unless_present = "blame",
+            conflicts_with = "blame"
+        )]
+        commit: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "FILE:LINE",
+            help = "Look up the commit that last changed the line instead"
+        )]
+        blame: Option<String>,
+    },
+
+    /// Approve a request
+    Approve {
+        #[arg(help = "The ID of the MR or PR, instead of the one for the current branch")]
+        request_id: Option<String>,
+    },
+
+    /// Withdraw your approval of a request
+    Unapprove {
+        #[arg(help = "The ID of the MR or PR, instead of the one for the current branch")]
+        request_id: Option<String>,
+    },
+
+    /// Ask for changes to be made to a request before it's merged
+    RequestChanges {
+        #[arg(help = "The ID of the MR or PR, instead of the one for the current branch")]
+        request_id: Option<String>,
+
+        #[arg(short, long, help = "The changes being requested")]
+        message: String,
+    },
+
+    /// Edit a request's title, description, labels, reviewers, assignees, milestone, and draft
+    /// state in your editor
+    Edit {
+        #[arg(help = "The ID of the MR or PR, instead of the one for the current branch")]
+        request_id: Option<String>,
+
+        #[arg(
+            short = 'F',
+            long,
+            conflicts_with = "print",
+            help = "Read the edited request from a file ('-' for stdin) instead of the editor"
+        )]
+        file: Option<String>,
+
+        #[arg(
+            long,
+            help = "Print the request in the format used for editing, then exit"
+        )]
+        print: bool,
+    },
+
+    /// Mark a draft request as ready for review
+    Ready {
+        #[arg(help = "The ID of the MR or PR, instead of the one for the current branch")]
+        request_id: Option<String>,
+    },
+
+    /// Convert a request back to a draft
+    Draft {
+        #[arg(help = "The ID of the MR or PR, instead of the one for the current branch")]
+        request_id: Option<String>,
+    },
+
+    /// Close a request without merging it
+    Close {
+        #[arg(help = "The ID of the MR or PR, instead of the one for the current branch")]
+        request_id: Option<String>,
+    },
+
+    /// Reopen a closed request
+    Reopen {
+        #[arg(help = "The ID of the MR or PR, instead of the one for the current branch")]
+        request_id: Option<String>,
+    },
+
+    /// Change the branch a request targets, or move every open request from one branch to another
+    Retarget {
+        #[arg(
+            required_unless_present = "from",
+            conflicts_with = "from",
+            help = "The branch to target"
+        )]
+        branch: Option<String>,
+
+        #[arg(
+            conflicts_with = "from",
+            help = "The ID of the MR or PR, instead of the one for the current branch"
+        )]
+        request_id: Option<String>,
+
+        #[arg(
+            long,
+            requires = "to",
+            help = "Retarget all the open requests targeting this branch"
+        )]
+        from: Option<String>,
+
+        #[arg(
+            long,
+            requires = "from",
+            help = "The branch to retarget the requests to"
+        )]
+        to: Option<String>,
+
+        #[arg(
+            long,
+            requires = "from",
+            help = "Only retarget requests by this author"
+        )]
+        author: Option<String>,
+
+        #[arg(
+            long,
+            requires = "from",
+            help = "Only retarget requests with this label"
+        )]
+        label: Option<String>,
+
+        #[arg(
+            long,
+            requires = "from",
+            help = "List the requests without retargeting them"
+        )]
+        dry_run: bool,
+
+        #[arg(short, long, requires = "from", help = "Don't ask for confirmation")]
+        yes: bool,
+    },
+
+    /// Comment on a request, writing the comment in your editor unless it's given
+    Comment {
+        #[arg(help = "The ID of the MR or PR, instead of the one for the current branch")]
+        request_id: Option<String>,
+
+        #[arg(short, long, help = "The comment to leave")]
+        message: Option<String>,
+
+        #[arg(
+            long,
+            requires = "line",
+            help = "Comment on a line of this file's diff instead of the request as a whole"
+        )]
+        file: Option<String>,
+
+        #[arg(
+            long,
+            requires = "file",
+            help = "The line of the file to comment on, as numbered in the request's version"
+        )]
+        line: Option<i64>,
+    },
+
+    /// Merge a request, refusing if the local checkout of it has unpushed commits
+    Merge {
+        #[arg(help = "The ID of the MR or PR, instead of the one for the current branch")]
+        request_id: Option<String>,
+
+        #[arg(long, value_enum, default_value_t = MergeMethod::Merge, help = "How to merge")]
+        method: MergeMethod,
+
+        #[arg(
+            long,
+            help = "Merge once the pipeline succeeds (GitLab) or the requirements are met (GitHub)"
+        )]
+        auto: bool,
+
+        #[arg(long, help = "Delete the source branch after merging")]
+        delete_branch: bool,
+
+        #[arg(short, long, help = "The message for the merge or squashed commit")]
+        message: Option<String>,
+    },
+
+    /// Show the stack a request belongs to, where each request targets the source branch of the
+    /// one below it
+    Stack {
+        #[arg(help = "The ID of the MR or PR, instead of the one for the current branch")]
+        request_id: Option<String>,
+
+        #[arg(
+            long,
+            help = "Check out a branch for every open request in the stack, ending on this one"
+        )]
+        checkout: bool,
+
+        #[arg(
+            long,
+            conflicts_with = "checkout",
+            help = "Rebase each request onto the latest version of the one it's stacked on, or onto \
+                    that one's target once it's merged, then push and retarget them"
+        )]
+        rebase: bool,
+
+        #[arg(
+            long,
+            requires = "rebase",
+            help = "Only rebase the local branches, without pushing or retargeting them"
+        )]
+        no_push: bool,
+    },
+
+    /// Cherry-pick a request's commits, or its merge commit once merged, onto another branch
+    Backport {
+        #[arg(
+            conflicts_with_all = ["resume", "skip", "abort"],
+            help = "The ID of the MR or PR, instead of the one for the current branch"
+        )]
+        request_id: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "BRANCH",
+            required_unless_present_any = ["resume", "skip", "abort"],
+            conflicts_with_all = ["resume", "skip", "abort"],
+            help = "The branch to backport onto"
+        )]
+        onto: Option<String>,
+
+        #[arg(
+            long,
+            conflicts_with_all = ["resume", "skip", "abort"],
+            help = "Open a request against the branch once the commits are picked"
+        )]
+        create: bool,
+
+        #[arg(
+            long = "continue",
+            conflicts_with_all = ["skip", "abort"],
+            help = "Carry on with the backport after resolving a conflict"
+        )]
+        resume: bool,
+
+        #[arg(
+            long,
+            conflicts_with = "abort",
+            help = "Leave out the commit that stopped the backport, then carry on"
+        )]
+        skip: bool,
+
+        #[arg(long, help = "Give up on the backport in progress")]
+        abort: bool,
+    },
+
+    /// List the discussion threads on a request
+    Comments {
+        #[arg(help = "The ID of the MR or PR, instead of the one for the current branch")]
+        request_id: Option<String>,
+
+        #[arg(long, help = "Print the threads as JSON")]
+        json: bool,
+    },
+
+    /// Open a request for the current branch, pushing it first if needed
+    Create {
+        #[arg(
+            short,
+            long,
+            value_name = "BRANCH",
+            help = "The branch to merge into, instead of the remote's default branch"
+        )]
+        target: Option<String>,
+
+        #[arg(
+            long,
+            help = "The title, instead of the one taken from the commit messages"
+        )]
+        title: Option<String>,
+
+        #[arg(
+            short,
+            long,
+            help = "Edit the title and description, starting from the repository's template"
+        )]
+        edit: bool,
+
+        #[arg(long, help = "Open the request as a draft")]
+        draft: bool,
+    },
+}
+
 #[derive(Debug, Parser)]
 #[command(
     bin_name = "git req",
     author,
     version,
     about = "Switch between merge/pull requests in your GitLab and GitHub repositories with just the request ID",
-    long_about = None
+    long_about = None,
+    subcommand_negates_reqs = true
 )]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+
     #[arg(
         short = 'u',
         long = "use-remote",
+        global = true,
         help = "The remote to be used for this command"
     )]
     pub remote_name: Option<String>,
@@ -22,6 +384,7 @@ pub struct Cli {
         long,
         help = "List all open requests against the repository",
         conflicts_with_all=[
+            "search",
             "new_project_id",
             "clear_project_id",
             "new_domain_key",
@@ -32,6 +395,80 @@ pub struct Cli {
     )]
     pub list: bool,
 
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        num_args = 0..=1,
+        default_missing_value = "60",
+        requires = "list",
+        help = "Keep refreshing the listed requests, highlighting what changed between refreshes"
+    )]
+    pub watch: Option<u64>,
+
+    #[arg(
+        long,
+        requires = "list",
+        conflicts_with_all = ["remote_name", "watch"],
+        help = "List the open requests of every remote in the repository"
+    )]
+    pub all_remotes: bool,
+
+    #[arg(
+        long,
+        requires = "list",
+        conflicts_with_all = ["all_remotes", "watch"],
+        help = "List the open requests of every project in the remote's group or organization"
+    )]
+    pub whole_group: bool,
+
+    #[arg(
+        long,
+        value_name = "QUERY",
+        help = "Search the open requests by title and description",
+        conflicts_with_all=[
+            "new_project_id",
+            "clear_project_id",
+            "new_domain_key",
+            "clear_domain_key",
+            "new_default_remote",
+            "generate_completions",
+        ]
+    )]
+    pub search: Option<String>,
+
+    #[arg(
+        long,
+        help = "Exclude draft requests when listing or searching",
+        conflicts_with = "drafts_only"
+    )]
+    pub no_drafts: bool,
+
+    #[arg(long, help = "Only include draft requests when listing or searching")]
+    pub drafts_only: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Sort listed or searched requests by the given field"
+    )]
+    pub sort: Option<SortKey>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = SortOrder::Asc,
+        requires = "sort",
+        help = "The direction to sort requests in"
+    )]
+    pub order: SortOrder,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Group listed or searched requests into sections by the given field"
+    )]
+    pub group_by: Option<GroupBy>,
+
     #[arg(
         long = "set-project-id",
         help = "Set a project ID for the current repository",
@@ -92,6 +529,29 @@ pub struct Cli {
     )]
     pub generate_completions: Option<Shell>,
 
+    #[arg(
+        long,
+        requires = "request_id",
+        help = "Check out the request by applying its patches with `git am`, instead of fetching its branch"
+    )]
+    pub as_patches: bool,
+
+    #[arg(
+        long,
+        value_name = "BASE",
+        requires = "as_patches",
+        conflicts_with = "stdout",
+        help = "Apply the patches on top of this branch or commit, instead of the request's target branch"
+    )]
+    pub onto: Option<String>,
+
+    #[arg(
+        long,
+        requires = "as_patches",
+        help = "Write the patches to stdout as an mbox instead of applying them"
+    )]
+    pub stdout: bool,
+
     #[arg(
         help = "The ID of the MR or PR, or '-' to reference the one previously checked out",
         required_unless_present_any=[
@@ -100,11 +560,13 @@ pub struct Cli {
           "new_domain_key",
           "clear_domain_key",
           "list",
+          "search",
           "new_default_remote",
           "generate_completions",
         ],
         conflicts_with_all=[
             "list",
+            "search",
             "new_project_id",
             "clear_project_id",
             "new_domain_key",
@@ -0,0 +1,188 @@
+//! Open new requests from the current branch
+use crate::remotes::NewRequest;
+use crate::{abort, get_remote_hard, git};
+use anyhow::{anyhow, Result};
+use colored::*;
+use log::{debug, info};
+use std::fs;
+
+/// Where GitHub and GitLab look for request templates, in order of preference
+const TEMPLATE_PATHS: [&str; 6] = [
+    ".github/pull_request_template.md",
+    ".github/PULL_REQUEST_TEMPLATE.md",
+    "PULL_REQUEST_TEMPLATE.md",
+    "docs/pull_request_template.md",
+    ".gitlab/merge_request_templates/Default.md",
+    ".gitlab/merge_request_templates/default.md",
+];
+
+/// How the new request should be opened
+#[derive(Debug)]
+pub struct CreateOptions {
+    pub target: Option<String>,
+    pub title: Option<String>,
+    pub edit: bool,
+    pub draft: bool,
+}
+
+/// Derive a title and description from the commit messages, oldest first. A lone commit provides
+/// both; with several, the first one's subject is the title and all of them are listed.
+fn describe_commits(messages: &[String]) -> (String, String) {
+    let subject = |message: &str| String::from(message.lines().next().unwrap_or_default().trim());
+    match messages {
+        [] => (String::new(), String::new()),
+        [message] => {
+            let (title, body) = message.split_once('\n').unwrap_or((message, ""));
+            (String::from(title.trim()), String::from(body.trim()))
+        }
+        [first, ..] => {
+            let description = messages
+                .iter()
+                .map(|message| format!("- {}", subject(message)))
+                .collect::<Vec<_>>()
+                .join("\n");
+            (subject(first), description)
+        }
+    }
+}
+
+/// Read the repository's request template, if it has one
+fn find_template() -> Option<String> {
+    let workdir = git::get_workdir()?;
+    TEMPLATE_PATHS.iter().find_map(|path| {
+        let template = fs::read_to_string(workdir.join(path)).ok()?;
+        debug!("Using the request template at {}", path);
+        Some(template)
+    })
+}
+
+/// Format the title and description for editing
+fn format_edit_message(title: &str, description: &str) -> String {
+    format!("{}\n\n{}\n", title, description.trim_end())
+}
+
+/// Split the edited message into a title and description. The first line is the title, and
+/// everything after it is the description.
+fn parse_edit_message(message: &str) -> Result<(String, String)> {
+    let message = message.trim_start();
+    let (title, description) = message.split_once('\n').unwrap_or((message, ""));
+    let title = title.trim();
+    if title.is_empty() {
+        return Err(anyhow!("the title is empty"));
+    }
+    Ok((String::from(title), String::from(description.trim())))
+}
+
+/// Let the user edit the title and description in their editor
+fn edit_description(title: &str, description: &str) -> Result<(String, String)> {
+    let message = git::edit_text("REQ_EDITMSG", &format_edit_message(title, description))?;
+    parse_edit_message(&message)
+}
+
+/// Push the current branch if needed, then open a request for it
+pub fn create_request(remote_name: &str, options: &CreateOptions) {
+    let branch = git::get_current_branch_name().unwrap_or_else(|| {
+        abort("Not on a branch");
+    });
+    info!("Creating a request for {}", branch);
+    let mut remote = get_remote_hard(remote_name, true);
+    let target = match &options.target {
+        Some(target) => target.clone(),
+        None => remote.get_default_branch().unwrap_or_else(|error| {
+            let message = format!("There was a problem finding the default branch: {}", error);
+            abort(&message);
+        }),
+    };
+    if branch == target {
+        abort("The current branch is the target branch");
+    }
+    let messages =
+        git::get_commit_messages(&format!("{}/{}", remote_name, target)).unwrap_or_else(|error| {
+            debug!("Couldn't read the branch's commits: {}", error);
+            vec![]
+        });
+    let (mut title, mut description) = describe_commits(&messages);
+    if let Some(override_title) = &options.title {
+        title = override_title.clone();
+    }
+    if options.edit {
+        if let Some(template) = find_template() {
+            description = template;
+        }
+        (title, description) = edit_description(&title, &description).unwrap_or_else(|error| {
+            let message = format!("Not creating the request: {}", error);
+            abort(&message);
+        });
+    }
+    if title.is_empty() {
+        abort("A title is required; pass --title or --edit");
+    }
+    if !git::is_branch_pushed(remote_name, &branch) {
+        git::push_branch(remote_name, &branch).unwrap_or_else(|error| {
+            let message = format!("There was a problem pushing the branch: {}", error);
+            abort(&message);
+        });
+    }
+    let new_req = NewRequest {
+        title,
+        description,
+        source_branch: branch,
+        target_branch: target,
+        draft: options.draft,
+    };
+    let mr = remote.create_req(&new_req).unwrap_or_else(|error| {
+        let message = format!("There was a problem creating the request: {}", error);
+        abort(&message);
+    });
+    println!(
+        "Created request {}: {}",
+        mr.id.to_string().green(),
+        mr.web_url.underline()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_commits_single() {
+        let messages = vec![String::from("Add create\n\nOpens a request.\n")];
+        assert_eq!(
+            (String::from("Add create"), String::from("Opens a request.")),
+            describe_commits(&messages)
+        );
+    }
+
+    #[test]
+    fn test_describe_commits_several() {
+        let messages = vec![
+            String::from("Add create\n\nOpens a request.\n"),
+            String::from("Fix typo\n"),
+        ];
+        assert_eq!(
+            (
+                String::from("Add create"),
+                String::from("- Add create\n- Fix typo")
+            ),
+            describe_commits(&messages)
+        );
+    }
+
+    #[test]
+    fn test_parse_edit_message() {
+        let message = format_edit_message("Add create", "## Summary\n\nOpens a request.");
+        assert_eq!(
+            (
+                String::from("Add create"),
+                String::from("## Summary\n\nOpens a request.")
+            ),
+            parse_edit_message(&message).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_edit_message_empty() {
+        assert!(parse_edit_message("\n\n").is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! Locate lines within request diffs
+use crate::remotes::DiffPosition;
+use anyhow::{anyhow, Result};
+
+/// Parse the line numbers of a hunk header (`@@ -old,count +new,count @@`)
+fn parse_hunk_header(line: &str) -> Option<(i64, i64)> {
+    let ranges = line.strip_prefix("@@ ")?.split(" @@").next()?;
+    let (old, new) = ranges.split_once(' ')?;
+    let start = |range: &str, sign: char| -> Option<i64> {
+        range.strip_prefix(sign)?.split(',').next()?.parse().ok()
+    };
+    Some((start(old, '-')?, start(new, '+')?))
+}
+
+/// Get the path from a `---` or `+++` file header, or `None` for `/dev/null`
+fn parse_file_header<'a>(header: &'a str, prefix: &str) -> Option<&'a str> {
+    // Git ends the path with a tab when it contains spaces
+    let path = header.trim_end_matches('\t');
+    path.strip_prefix(prefix)
+}
+
+/// Find where a line of the new version of the file falls within its diff. Only lines within the
+/// diff's hunks can be commented on; added lines only exist on the new side, while unchanged
+/// context lines have a number on both.
+pub fn find_line_position(diff: &str, line: i64) -> Result<DiffPosition> {
+    let mut old_path = None;
+    let mut new_path = None;
+    let mut lines = None;
+    let mut found_hunk = false;
+    for diff_line in diff.lines() {
+        if let Some((old_start, new_start)) = parse_hunk_header(diff_line) {
+            lines = Some((old_start, new_start));
+            found_hunk = true;
+            continue;
+        }
+        let Some((old_line, new_line)) = lines.as_mut() else {
+            if let Some(header) = diff_line.strip_prefix("--- ") {
+                old_path = parse_file_header(header, "a/");
+            } else if let Some(header) = diff_line.strip_prefix("+++ ") {
+                new_path = parse_file_header(header, "b/");
+            }
+            continue;
+        };
+        let (at_old, at_new) = match diff_line.chars().next() {
+            Some('+') => (None, Some(*new_line)),
+            Some('-') => (Some(*old_line), None),
+            // Markers such as "\ No newline at end of file"
+            Some('\\') => continue,
+            _ => (Some(*old_line), Some(*new_line)),
+        };
+        if at_new == Some(line) {
+            let new_path = new_path.ok_or_else(|| anyhow!("the file is deleted by the request"))?;
+            return Ok(DiffPosition {
+                // New files are given the same path on both sides
+                old_path: String::from(old_path.unwrap_or(new_path)),
+                new_path: String::from(new_path),
+                old_line: at_old,
+                new_line: line,
+            });
+        }
+        if at_old.is_some() {
+            *old_line += 1;
+        }
+        if at_new.is_some() {
+            *new_line += 1;
+        }
+    }
+    if !found_hunk {
+        return Err(anyhow!("the request doesn't change the file"));
+    }
+    Err(anyhow!(
+        "line {} isn't part of the request's diff; only changed lines and the lines around them \
+         can be commented on",
+        line
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODIFIED: &str = include_str!("../tests/fixtures/modified.diff");
+    const ADDED: &str = include_str!("../tests/fixtures/added.diff");
+    const RENAMED: &str = include_str!("../tests/fixtures/renamed.diff");
+
+    fn position(
+        old_path: &str,
+        new_path: &str,
+        old_line: Option<i64>,
+        new_line: i64,
+    ) -> DiffPosition {
+        DiffPosition {
+            old_path: String::from(old_path),
+            new_path: String::from(new_path),
+            old_line,
+            new_line,
+        }
+    }
+
+    #[test]
+    fn test_parse_hunk_header() {
+        assert_eq!(
+            Some((118, 119)),
+            parse_hunk_header("@@ -118,6 +119,9 @@ pub fn x() {")
+        );
+        assert_eq!(Some((0, 1)), parse_hunk_header("@@ -0,0 +1 @@"));
+        assert_eq!(None, parse_hunk_header("+@@ not a header"));
+    }
+
+    #[test]
+    fn test_find_line_position_context() {
+        assert_eq!(
+            position("src/git.rs", "src/git.rs", Some(14), 15),
+            find_line_position(MODIFIED, 15).unwrap()
+        );
+        // Context after an addition in the second hunk
+        assert_eq!(
+            position("src/git.rs", "src/git.rs", Some(121), 125),
+            find_line_position(MODIFIED, 125).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_find_line_position_added() {
+        assert_eq!(
+            position("src/git.rs", "src/git.rs", None, 14),
+            find_line_position(MODIFIED, 14).unwrap()
+        );
+        assert_eq!(
+            position("src/git.rs", "src/git.rs", None, 124),
+            find_line_position(MODIFIED, 124).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_find_line_position_outside_hunks() {
+        assert!(find_line_position(MODIFIED, 50).is_err());
+        assert!(find_line_position(MODIFIED, 128).is_err());
+        assert!(find_line_position("", 1).is_err());
+    }
+
+    #[test]
+    fn test_find_line_position_new_file() {
+        assert_eq!(
+            position("src/markdown.rs", "src/markdown.rs", None, 4),
+            find_line_position(ADDED, 4).unwrap()
+        );
+        assert!(find_line_position(ADDED, 5).is_err());
+    }
+
+    #[test]
+    fn test_find_line_position_renamed() {
+        assert_eq!(
+            position("src/remote.rs", "src/remotes/mod.rs", None, 1),
+            find_line_position(RENAMED, 1).unwrap()
+        );
+        assert_eq!(
+            position("src/remote.rs", "src/remotes/mod.rs", Some(4), 4),
+            find_line_position(RENAMED, 4).unwrap()
+        );
+    }
+}
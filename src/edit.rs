@@ -0,0 +1,306 @@
+//! Edit a request's title, description, and metadata as a single text file
+use crate::remotes::{MergeRequest, ReqUpdate};
+use crate::{abort, get_remote_hard, get_request_id_or_current, git};
+use anyhow::{anyhow, Result};
+use colored::*;
+use log::{debug, info};
+use std::fs;
+use std::io::{self, Read};
+
+/// The fence around the front matter
+const FENCE: &str = "---";
+
+/// The parts of a request that can be edited
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReqMetadata {
+    pub title: String,
+    pub description: String,
+    pub labels: Vec<String>,
+    pub reviewers: Vec<String>,
+    pub assignees: Vec<String>,
+    pub milestone: Option<String>,
+    pub draft: bool,
+}
+
+impl ReqMetadata {
+    pub fn from_req(mr: &MergeRequest) -> Self {
+        ReqMetadata {
+            title: mr.title.clone(),
+            description: mr.description.clone().unwrap_or_default(),
+            labels: mr.labels.clone(),
+            reviewers: mr.reviewers.clone(),
+            assignees: mr.assignees.clone(),
+            milestone: mr.milestone.clone(),
+            draft: mr.draft,
+        }
+    }
+}
+
+/// Write a list field with one item per line, so that items can hold any character
+fn format_list(message: &mut String, name: &str, items: &[String]) {
+    message.push_str(&format!("{}:\n", name));
+    for item in items {
+        message.push_str(&format!("  - {}\n", item));
+    }
+}
+
+/// Format the metadata as front matter, followed by the title and description
+pub fn format_edit_message(metadata: &ReqMetadata) -> String {
+    let mut message = format!("{}\n", FENCE);
+    format_list(&mut message, "labels", &metadata.labels);
+    format_list(&mut message, "reviewers", &metadata.reviewers);
+    format_list(&mut message, "assignees", &metadata.assignees);
+    message.push_str(&format!(
+        "milestone: {}\n",
+        metadata.milestone.as_deref().unwrap_or_default()
+    ));
+    message.push_str(&format!("draft: {}\n", metadata.draft));
+    message.push_str(&format!("{}\n{}\n", FENCE, metadata.title));
+    if !metadata.description.trim().is_empty() {
+        message.push_str(&format!("\n{}\n", metadata.description.trim_end()));
+    }
+    message
+}
+
+/// Parse a `true`/`false` field, accepting the usual spellings
+fn parse_bool(name: &str, value: &str) -> Result<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "y" => Ok(true),
+        "false" | "no" | "n" | "" => Ok(false),
+        _ => Err(anyhow!(
+            "'{}' should be true or false, not '{}'",
+            name,
+            value
+        )),
+    }
+}
+
+/// Parse the front matter, title, and description back into metadata. Fields missing from the
+/// front matter are left empty.
+pub fn parse_edit_message(message: &str) -> Result<ReqMetadata> {
+    let mut lines = message.trim_start().lines();
+    if lines.next().map(str::trim_end) != Some(FENCE) {
+        return Err(anyhow!("the front matter should start with '{}'", FENCE));
+    }
+    let mut metadata = ReqMetadata::default();
+    let mut list: Option<&mut Vec<String>> = None;
+    loop {
+        let line = lines
+            .next()
+            .ok_or_else(|| anyhow!("the front matter should end with '{}'", FENCE))?;
+        if line.trim_end() == FENCE {
+            break;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(item) = line.trim_start().strip_prefix("- ") {
+            let list = list
+                .as_mut()
+                .ok_or_else(|| anyhow!("the item '{}' isn't part of a list", item))?;
+            list.push(String::from(item.trim()));
+            continue;
+        }
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| anyhow!("expected a 'field: value' line, not '{}'", line))?;
+        let (name, value) = (name.trim(), value.trim());
+        list = None;
+        match name {
+            "labels" | "reviewers" | "assignees" => {
+                if !value.is_empty() {
+                    return Err(anyhow!("list the {} one per line, as '  - name'", name));
+                }
+                list = Some(match name {
+                    "labels" => &mut metadata.labels,
+                    "reviewers" => &mut metadata.reviewers,
+                    _ => &mut metadata.assignees,
+                });
+            }
+            "milestone" => {
+                metadata.milestone = Some(String::from(value)).filter(|value| !value.is_empty())
+            }
+            "draft" => metadata.draft = parse_bool(name, value)?,
+            _ => return Err(anyhow!("unknown field '{}'", name)),
+        }
+    }
+    let rest: Vec<&str> = lines.collect();
+    let rest = rest.join("\n");
+    let rest = rest.trim_start_matches('\n');
+    let (title, description) = rest.split_once('\n').unwrap_or((rest, ""));
+    metadata.title = String::from(title.trim());
+    if metadata.title.is_empty() {
+        return Err(anyhow!("the title is empty"));
+    }
+    metadata.description = String::from(description.trim());
+    Ok(metadata)
+}
+
+/// Determine if two lists have the same items, in any order
+fn same_items(first: &[String], second: &[String]) -> bool {
+    let mut first = first.to_vec();
+    let mut second = second.to_vec();
+    first.sort();
+    second.sort();
+    first == second
+}
+
+/// Work out the update that turns the old metadata into the new
+pub fn diff_metadata(old: &ReqMetadata, new: &ReqMetadata) -> ReqUpdate {
+    let changed_list =
+        |old: &[String], new: &[String]| (!same_items(old, new)).then(|| new.to_vec());
+    ReqUpdate {
+        title: (old.title != new.title).then(|| new.title.clone()),
+        description: (old.description.trim() != new.description.trim())
+            .then(|| new.description.clone()),
+        labels: changed_list(&old.labels, &new.labels),
+        reviewers: changed_list(&old.reviewers, &new.reviewers),
+        assignees: changed_list(&old.assignees, &new.assignees),
+        milestone: (old.milestone != new.milestone).then(|| new.milestone.clone()),
+        draft: (old.draft != new.draft).then_some(new.draft),
+        ..ReqUpdate::default()
+    }
+}
+
+/// Read the edited message from a file, or from stdin for `-`
+fn read_message_file(path: &str) -> Result<String> {
+    if path == "-" {
+        let mut message = String::new();
+        io::stdin().read_to_string(&mut message)?;
+        return Ok(message);
+    }
+    Ok(fs::read_to_string(path)?)
+}
+
+/// How a request should be edited
+#[derive(Debug)]
+pub struct EditOptions {
+    /// Read the edited request from this file instead of opening the editor
+    pub file: Option<String>,
+    /// Print the request for editing, without changing it
+    pub print: bool,
+}
+
+/// Edit the given request, or the one for the current branch, then apply whatever changed
+pub fn edit_request(remote_name: &str, request_id: Option<String>, options: &EditOptions) {
+    let mut remote = get_remote_hard(remote_name, true);
+    debug!("Found remote: {}", remote);
+    let mr_id = get_request_id_or_current(remote_name, remote.as_mut(), request_id);
+    info!("Editing MR: {}", mr_id);
+    let mr = remote.get_req(mr_id).unwrap_or_else(|error| {
+        let message = format!("There was a problem querying the request: {}", &error);
+        abort(&message);
+    });
+    let old = ReqMetadata::from_req(&mr);
+    if options.print {
+        print!("{}", format_edit_message(&old));
+        return;
+    }
+    let message = match &options.file {
+        Some(path) => read_message_file(path),
+        None => git::edit_text("REQ_EDITMSG", &format_edit_message(&old)),
+    };
+    let new = message
+        .and_then(|message| parse_edit_message(&message))
+        .unwrap_or_else(|error| {
+            let message = format!("Not editing the request: {}", error);
+            abort(&message);
+        });
+    let update = diff_metadata(&old, &new);
+    if update.is_empty() {
+        println!("Request {} is unchanged", mr_id.to_string().green());
+        return;
+    }
+    debug!("Applying {:?}", update);
+    remote.update_req(mr_id, &update).unwrap_or_else(|error| {
+        let message = format!("There was a problem updating the request: {}", error);
+        abort(&message);
+    });
+    println!("Updated request {}", mr_id.to_string().green());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_metadata() -> ReqMetadata {
+        ReqMetadata {
+            title: String::from("Add edit"),
+            description: String::from(
+                "## Summary\n\nEdits requests.\n\n---\n\nlabels: not a field",
+            ),
+            labels: vec![String::from("needs review"), String::from("area: ui, cli")],
+            reviewers: vec![String::from("reviewer")],
+            assignees: vec![],
+            milestone: Some(String::from("1.3: the big one")),
+            draft: true,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let metadata = make_metadata();
+        assert_eq!(
+            metadata,
+            parse_edit_message(&format_edit_message(&metadata)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_round_trip_empty() {
+        let metadata = ReqMetadata {
+            title: String::from("Add edit"),
+            ..ReqMetadata::default()
+        };
+        let message = format_edit_message(&metadata);
+        assert_eq!(
+            "---\nlabels:\nreviewers:\nassignees:\nmilestone: \ndraft: false\n---\nAdd edit\n",
+            message
+        );
+        assert_eq!(metadata, parse_edit_message(&message).unwrap());
+    }
+
+    #[test]
+    fn test_parse_edit_message_lenient() {
+        let message = "\n---\nlabels:\n- bug\n\ndraft: yes\n---\n\nFix the bug\nIt's fixed.\n";
+        let metadata = parse_edit_message(message).unwrap();
+        assert_eq!(vec!["bug"], metadata.labels);
+        assert!(metadata.draft);
+        assert_eq!(None, metadata.milestone);
+        assert_eq!("Fix the bug", metadata.title);
+        assert_eq!("It's fixed.", metadata.description);
+    }
+
+    #[test]
+    fn test_parse_edit_message_errors() {
+        assert!(parse_edit_message("Fix the bug").is_err());
+        assert!(parse_edit_message("---\nlabels: bug\n---\nFix the bug").is_err());
+        assert!(parse_edit_message("---\nowner: aru\n---\nFix the bug").is_err());
+        assert!(parse_edit_message("---\ndraft: maybe\n---\nFix the bug").is_err());
+        assert!(parse_edit_message("---\n  - bug\n---\nFix the bug").is_err());
+        assert!(parse_edit_message("---\ndraft: true\n").is_err());
+        assert!(parse_edit_message("---\n---\n\n").is_err());
+    }
+
+    #[test]
+    fn test_diff_metadata() {
+        let old = make_metadata();
+        let mut new = old.clone();
+        assert!(diff_metadata(&old, &new).is_empty());
+        new.labels.reverse();
+        new.description.push('\n');
+        assert!(diff_metadata(&old, &new).is_empty());
+        new.milestone = None;
+        new.draft = false;
+        new.assignees = vec![String::from("aru")];
+        assert_eq!(
+            ReqUpdate {
+                milestone: Some(None),
+                draft: Some(false),
+                assignees: Some(vec![String::from("aru")]),
+                ..ReqUpdate::default()
+            },
+            diff_metadata(&old, &new)
+        );
+    }
+}
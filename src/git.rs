@@ -1,11 +1,12 @@
 use anyhow::{anyhow, Result};
 use logchop::OptionLogger;
-use std::path::Path;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::str;
 use std::{collections::HashSet, convert::TryInto};
 
 use duct::cmd;
-use git2::{Config, Repository};
+use git2::{BranchType, Config, Oid, Repository};
 use log::{debug, trace, warn};
 
 /// Update old `req.key` config format to include remote name, i.e, `req.remote_name.key`
@@ -88,6 +89,13 @@ pub fn set_project_config(field_name: &str, value: &str) {
     cfg.set_str(&format!("req.{}", field_name), value).unwrap();
 }
 
+/// Delete the entry for the project-local git-req config field with the provided name
+pub fn delete_project_config(field_name: &str) -> Result<(), git2::Error> {
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    let mut cfg = repo.config().unwrap();
+    cfg.remove(&format!("req.{}", field_name))
+}
+
 /// Get a value for the given global git-req config
 pub fn get_req_config(domain: &str, field: &str) -> Option<String> {
     let slug = slugify_domain(domain);
@@ -135,17 +143,27 @@ pub fn guess_default_remote_name() -> Result<String> {
     }
 }
 
-/// Get the ID of the previous MR that had been checked out using git-req
-pub fn get_previous_mr_id() -> Result<i64> {
+/// Read the MR ID stored in the given git-req history ref
+fn get_history_mr_id(ref_name: &str) -> Result<i64> {
     let repo = Repository::open_from_env().expect("Couldn't find repository");
-    let testco = repo.find_reference("git-req/previous")?;
+    let testco = repo.find_reference(ref_name)?;
     let content = testco.peel_to_blob()?;
     let binary = content.content();
     let reqnum = i64::from_le_bytes(binary.try_into()?);
-    debug!("Loaded previous reference MR number: {}", reqnum);
+    debug!("Loaded {} reference MR number: {}", ref_name, reqnum);
     Ok(reqnum)
 }
 
+/// Get the ID of the previous MR that had been checked out using git-req
+pub fn get_previous_mr_id() -> Result<i64> {
+    get_history_mr_id("git-req/previous")
+}
+
+/// Get the ID of the MR most recently checked out using git-req
+pub fn get_current_mr_id() -> Result<i64> {
+    get_history_mr_id("git-req/current")
+}
+
 /// Push a new `current` history ref, moving the existing `current` to `previous`
 pub fn push_current_ref(new_req_number: i64) -> Result<i64> {
     trace!("Storing refs for MR {}", new_req_number);
@@ -166,21 +184,17 @@ pub fn push_current_ref(new_req_number: i64) -> Result<i64> {
     Ok(new_req_number)
 }
 
-#[derive(Debug)]
-pub enum CheckoutResult {
-    BranchChanged,
-    BranchUnchanged,
+/// The state of a request's local branch relative to the request's head commit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalBranchStatus {
+    UpToDate,
+    Behind,
 }
 
-/// Check out a branch by name
-pub fn checkout_branch(
-    remote_name: &str,
-    remote_branch_name: &str,
-    local_branch_name: &str,
-    is_virtual_remote_branch: bool,
-) -> Result<CheckoutResult> {
-    let repo = Repository::open_from_env().expect("Couldn't find repository");
-    let local_branch_name = match get_project_config("defaultremote") {
+/// Get the name of the local branch that a remote's request branch is checked out as. Requests
+/// from non-default remotes are namespaced to avoid collisions.
+pub fn get_local_branch_name(remote_name: &str, local_branch_name: &str) -> String {
+    match get_project_config("defaultremote") {
         Some(default_remote_name) => {
             if remote_name != default_remote_name {
                 trace!("Non-default remote name requested: {}", remote_name);
@@ -194,7 +208,179 @@ pub fn checkout_branch(
             warn!("No default remote found. Using {}", remote_name);
             format!("{}/{}", remote_name, local_branch_name)
         }
+    }
+}
+
+/// Get the name of the currently checked out branch, if any
+pub fn get_current_branch_name() -> Option<String> {
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    let head = repo.head().ok()?;
+    if !head.is_branch() {
+        return None;
+    }
+    head.shorthand().map(String::from)
+}
+
+/// Get the name of the current branch's upstream on the given remote, without the remote's name
+pub fn get_current_upstream_branch_name(remote_name: &str) -> Option<String> {
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    let head = repo.head().ok()?;
+    let branch = repo
+        .find_branch(head.shorthand()?, BranchType::Local)
+        .ok()?;
+    let upstream = branch.upstream().ok()?;
+    let upstream_name = upstream.name().ok()??;
+    upstream_name
+        .strip_prefix(&format!("{}/", remote_name))
+        .map(String::from)
+}
+
+/// Resolve the revision to the full SHA of a commit
+pub fn resolve_commit(revision: &str) -> Result<String> {
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    let commit = repo.revparse_single(revision)?.peel_to_commit()?;
+    Ok(commit.id().to_string())
+}
+
+/// Get the number of parents that the revision's commit has
+pub fn get_parent_count(revision: &str) -> Result<usize> {
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    let commit = repo.revparse_single(revision)?.peel_to_commit()?;
+    Ok(commit.parent_count())
+}
+
+/// Get the full message of the revision's commit
+pub fn get_commit_message(revision: &str) -> Result<String> {
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    let commit = repo.revparse_single(revision)?.peel_to_commit()?;
+    Ok(String::from(commit.message().unwrap_or_default()))
+}
+
+/// Get the SHA of the commit that last changed the line of the file, as `git blame` sees it
+pub fn blame_line(path: &str, line: usize) -> Result<String> {
+    let range = format!("{},{}", line, line);
+    let output = cmd!("git", "blame", "--porcelain", "-L", range, "--", path)
+        .stderr_null()
+        .read()
+        .map_err(|_| anyhow!("Could not blame line {} of '{}'", line, path))?;
+    // The porcelain format starts with the SHA of the line's commit
+    output
+        .split_whitespace()
+        .next()
+        .map(String::from)
+        .ok_or_else(|| anyhow!("Could not blame line {} of '{}'", line, path))
+}
+
+/// Get the SHA of the commit that HEAD points at
+pub fn get_head_sha() -> Option<String> {
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    let head = repo.head().ok()?;
+    head.target().map(|oid| oid.to_string())
+}
+
+/// Determine if there's a local branch with the given name
+pub fn has_local_branch(local_branch_name: &str) -> bool {
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    let found = repo
+        .find_branch(local_branch_name, BranchType::Local)
+        .is_ok();
+    found
+}
+
+/// Compare a local branch against a request's head commit, returning `None` if there's no such
+/// branch. A head commit that hasn't been fetched yet counts as being behind.
+pub fn get_local_branch_status(
+    local_branch_name: &str,
+    head_sha: &str,
+) -> Option<LocalBranchStatus> {
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    let branch = repo
+        .find_branch(local_branch_name, BranchType::Local)
+        .ok()?;
+    let local_oid = branch.get().target()?;
+    let head_oid = match Oid::from_str(head_sha) {
+        Ok(oid) if repo.find_commit(oid).is_ok() => oid,
+        _ => {
+            trace!(
+                "Head {} of {} isn't available locally",
+                head_sha,
+                local_branch_name
+            );
+            return Some(LocalBranchStatus::Behind);
+        }
+    };
+    match repo.graph_ahead_behind(local_oid, head_oid) {
+        Ok((_, 0)) => Some(LocalBranchStatus::UpToDate),
+        Ok(_) => Some(LocalBranchStatus::Behind),
+        Err(err) => {
+            debug!(
+                "Couldn't compare {} to {}: {}",
+                local_branch_name, head_sha, err
+            );
+            None
+        }
+    }
+}
+
+/// How a local branch differs from its request's head
+#[derive(Debug)]
+pub struct BranchComparison {
+    /// The SHA of the branch's tip
+    pub tip: String,
+    /// The number of commits on the branch that the request doesn't have
+    pub unpushed: usize,
+    /// The number of commits on the request that the branch doesn't have
+    pub unreviewed: usize,
+}
+
+/// Fetch a request's head, then compare a local branch against it, returning `None` if there's no
+/// such branch. The fetched ref is deleted afterwards.
+pub fn compare_branch_to_request(
+    remote_name: &str,
+    mr_id: i64,
+    local_branch_name: &str,
+    remote_head_ref: &str,
+) -> Result<Option<BranchComparison>> {
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    let Ok(branch) = repo.find_branch(local_branch_name, BranchType::Local) else {
+        return Ok(None);
     };
+    let local_oid = branch
+        .get()
+        .target()
+        .ok_or_else(|| anyhow!("'{}' doesn't point at a commit", local_branch_name))?;
+    let prefix = format!("merge/{}", mr_id);
+    let head_ref = get_hidden_ref_name(&format!("{}/head", prefix));
+    let result = fetch_refs(remote_name, &[(remote_head_ref, &head_ref)]).and_then(|_| {
+        let head_oid = repo.refname_to_id(&head_ref)?;
+        let (unpushed, unreviewed) = repo.graph_ahead_behind(local_oid, head_oid)?;
+        Ok(BranchComparison {
+            tip: local_oid.to_string(),
+            unpushed,
+            unreviewed,
+        })
+    });
+    if let Err(err) = delete_hidden_refs(&prefix) {
+        debug!("Couldn't clean up the fetched refs: {}", err);
+    }
+    result.map(Some)
+}
+
+#[derive(Debug)]
+pub enum CheckoutResult {
+    BranchChanged,
+    BranchUnchanged,
+}
+
+/// Check out a branch by name
+pub fn checkout_branch(
+    remote_name: &str,
+    remote_branch_name: &str,
+    local_branch_name: &str,
+    is_virtual_remote_branch: bool,
+) -> Result<CheckoutResult> {
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    let local_branch_name = get_local_branch_name(remote_name, local_branch_name);
 
     let local_branch_exists = repo.revparse_single(&local_branch_name);
     match local_branch_exists {
@@ -251,3 +437,542 @@ pub fn checkout_branch(
         }
     }
 }
+
+/// Create a branch at the base and check it out
+pub fn create_branch(branch_name: &str, base: &str) -> Result<()> {
+    debug!("Creating {} at {}", branch_name, base);
+    match cmd!("git", "checkout", "-b", branch_name, base).run() {
+        Ok(_) => Ok(()),
+        Err(err) => Err(anyhow!(
+            "Could not create branch '{}' at '{}': {}",
+            branch_name,
+            base,
+            err
+        )),
+    }
+}
+
+/// Create a branch at the base and apply the mbox's patches to it with `git am`, falling back
+/// to a three-way merge for patches that don't apply cleanly
+pub fn apply_patches(branch_name: &str, base: &str, mbox: &str) -> Result<()> {
+    create_branch(branch_name, base)?;
+    if cmd!("git", "am", "-3").stdin_bytes(mbox).run().is_err() {
+        return Err(anyhow!(
+            "The patches didn't apply cleanly onto '{}'. Resolve the conflicts and run \
+             'git am --continue', or run 'git am --abort' to give up.",
+            base
+        ));
+    }
+    Ok(())
+}
+
+/// Get the full name of a ref in git-req's hidden namespace, which doesn't show up as branches
+pub fn get_hidden_ref_name(name: &str) -> String {
+    format!("refs/git-req/{}", name)
+}
+
+/// Point the ref at the revision's commit, creating or overwriting it
+pub fn update_ref(ref_name: &str, revision: &str) -> Result<()> {
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    let oid = repo.revparse_single(revision)?.peel_to_commit()?.id();
+    repo.reference(ref_name, oid, true, "git-req: update ref")?;
+    Ok(())
+}
+
+/// Fetch the remote refs into the given local refs, overwriting them if they exist
+pub fn fetch_refs(remote_name: &str, refspecs: &[(&str, &str)]) -> Result<()> {
+    let mut fetch_args = vec![
+        String::from("fetch"),
+        String::from("--quiet"),
+        String::from("--no-tags"),
+        String::from(remote_name),
+    ];
+    for (remote_ref, local_ref) in refspecs {
+        fetch_args.push(format!("+{}:{}", remote_ref, local_ref));
+    }
+    trace!("Fetching: {:?}", fetch_args);
+    match cmd("git", fetch_args).run() {
+        Ok(_) => Ok(()),
+        Err(err) => Err(anyhow!("Could not fetch from '{}': {}", remote_name, err)),
+    }
+}
+
+/// Fetch the remote's branch into its remote-tracking branch, returning the latter's short name
+pub fn fetch_branch(remote_name: &str, branch_name: &str) -> Result<String> {
+    let remote_ref = format!("refs/heads/{}", branch_name);
+    let tracking_ref = format!("refs/remotes/{}/{}", remote_name, branch_name);
+    fetch_refs(remote_name, &[(&remote_ref, &tracking_ref)])?;
+    Ok(format!("{}/{}", remote_name, branch_name))
+}
+
+/// Delete the hidden refs beneath the given name
+pub fn delete_hidden_refs(prefix: &str) -> Result<()> {
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    let glob = format!("{}/*", get_hidden_ref_name(prefix));
+    for reference in repo.references_glob(&glob)? {
+        let mut reference = reference?;
+        trace!("Deleting {:?}", reference.name());
+        reference.delete()?;
+    }
+    Ok(())
+}
+
+/// Find the commit where the two refs' histories diverged
+pub fn get_merge_base(first_ref: &str, second_ref: &str) -> Result<Oid> {
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    let first = repo.revparse_single(first_ref)?.peel_to_commit()?;
+    let second = repo.revparse_single(second_ref)?.peel_to_commit()?;
+    repo.merge_base(first.id(), second.id())
+        .map_err(|_| anyhow!("'{}' and '{}' share no history", first_ref, second_ref))
+}
+
+/// Determine if the first commit is the second or one of its ancestors
+pub fn is_ancestor(ancestor: &str, descendant: &str) -> Result<bool> {
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    let ancestor = repo.revparse_single(ancestor)?.peel_to_commit()?.id();
+    let descendant = repo.revparse_single(descendant)?.peel_to_commit()?.id();
+    Ok(ancestor == descendant || repo.graph_descendant_of(descendant, ancestor)?)
+}
+
+/// Find where the commit forked from the first of the upstream refs whose reflog records it, or
+/// fall back to its merge base with the first one. Going by the reflog finds the fork point even
+/// when the upstream has since been rewritten.
+pub fn find_fork_point(upstream_refs: &[&str], commit: &str) -> Result<String> {
+    for upstream_ref in upstream_refs {
+        if let Ok(fork_point) = cmd!("git", "merge-base", "--fork-point", upstream_ref, commit)
+            .stderr_null()
+            .read()
+        {
+            trace!("{} forked from {} at {}", commit, upstream_ref, fork_point);
+            return Ok(fork_point);
+        }
+    }
+    let upstream_ref = upstream_refs
+        .first()
+        .ok_or_else(|| anyhow!("No upstream to find the fork point from"))?;
+    Ok(get_merge_base(upstream_ref, commit)?.to_string())
+}
+
+/// Create a branch at the start point unless it already exists, returning whether it was created
+pub fn ensure_branch(branch_name: &str, start: &str) -> Result<bool> {
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    if repo.find_branch(branch_name, BranchType::Local).is_ok() {
+        return Ok(false);
+    }
+    let commit = repo.revparse_single(start)?.peel_to_commit()?;
+    debug!("Creating {} at {}", branch_name, commit.id());
+    repo.branch(branch_name, &commit, false)?;
+    Ok(true)
+}
+
+/// Check out an existing branch
+pub fn switch_branch(branch_name: &str) -> Result<()> {
+    match cmd!("git", "checkout", "--quiet", branch_name).run() {
+        Ok(_) => Ok(()),
+        Err(err) => Err(anyhow!("Could not check out '{}': {}", branch_name, err)),
+    }
+}
+
+/// Move the branch's commits after the old base onto the new base
+pub fn rebase_branch(branch_name: &str, new_base: &str, old_base: &str) -> Result<()> {
+    debug!(
+        "Rebasing {} from {} onto {}",
+        branch_name, old_base, new_base
+    );
+    match cmd!("git", "rebase", "--onto", new_base, old_base, branch_name).run() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(anyhow!("The rebase of '{}' stopped", branch_name)),
+    }
+}
+
+/// Force-push the local branch to the remote branch, provided the remote branch is still at the
+/// expected commit
+pub fn force_push_branch(
+    remote_name: &str,
+    local_branch_name: &str,
+    remote_branch_name: &str,
+    expected_sha: &str,
+) -> Result<()> {
+    let lease = format!(
+        "--force-with-lease=refs/heads/{}:{}",
+        remote_branch_name, expected_sha
+    );
+    let refspec = format!("{}:refs/heads/{}", local_branch_name, remote_branch_name);
+    debug!("Pushing {} to {}", refspec, remote_name);
+    match cmd!("git", "push", lease, remote_name, refspec).run() {
+        Ok(_) => Ok(()),
+        Err(err) => Err(anyhow!("Could not push '{}': {}", local_branch_name, err)),
+    }
+}
+
+/// Get the full name of the hidden ref that stores one of a request's revisions
+pub fn get_revision_ref_name(mr_id: i64, revision: &str) -> String {
+    get_hidden_ref_name(&format!("revisions/{}/{}", mr_id, revision))
+}
+
+/// Remember the commit as the request's latest revision. If the request has changed since it was
+/// last seen, the revision it replaces is kept as the previous one.
+pub fn record_revision(mr_id: i64, head: &str) -> Result<()> {
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    let head_oid = repo.revparse_single(head)?.peel_to_commit()?.id();
+    let latest_ref = get_revision_ref_name(mr_id, "latest");
+    if let Ok(latest_oid) = repo.refname_to_id(&latest_ref) {
+        if latest_oid == head_oid {
+            trace!("Request {} is unchanged at {}", mr_id, head_oid);
+            return Ok(());
+        }
+        let previous_ref = get_revision_ref_name(mr_id, "previous");
+        repo.reference(
+            &previous_ref,
+            latest_oid,
+            true,
+            "git-req: previous revision",
+        )?;
+        debug!(
+            "Moved revision {} of request {} to previous",
+            latest_oid, mr_id
+        );
+    }
+    repo.reference(&latest_ref, head_oid, true, "git-req: latest revision")?;
+    debug!("Recorded revision {} of request {}", head_oid, mr_id);
+    Ok(())
+}
+
+/// The local copies of a request's head and target branch
+struct FetchedRequest {
+    head_ref: String,
+    target_ref: String,
+}
+
+/// Fetch a request's head and target branch into hidden refs beneath the prefix, recording the
+/// head as the request's latest revision
+fn fetch_request(
+    remote_name: &str,
+    mr_id: i64,
+    prefix: &str,
+    remote_head_ref: &str,
+    target_branch: &str,
+) -> Result<FetchedRequest> {
+    let fetched = FetchedRequest {
+        head_ref: get_hidden_ref_name(&format!("{}/head", prefix)),
+        target_ref: get_hidden_ref_name(&format!("{}/target", prefix)),
+    };
+    let remote_target_ref = format!("refs/heads/{}", target_branch);
+    fetch_refs(
+        remote_name,
+        &[
+            (remote_head_ref, &fetched.head_ref),
+            (&remote_target_ref, &fetched.target_ref),
+        ],
+    )?;
+    record_revision(mr_id, &fetched.head_ref)?;
+    Ok(fetched)
+}
+
+/// Fetch a request's head and target branch, then show the changes the request makes since it
+/// diverged from the target. The diff goes through git so that the user's pager and diff
+/// settings apply. The fetched refs are deleted afterwards.
+pub fn diff_request(
+    remote_name: &str,
+    mr_id: i64,
+    remote_head_ref: &str,
+    target_branch: &str,
+    stat: bool,
+) -> Result<()> {
+    let prefix = format!("diff/{}", mr_id);
+    let result = fetch_request(remote_name, mr_id, &prefix, remote_head_ref, target_branch)
+        .and_then(|fetched| {
+            let merge_base = get_merge_base(&fetched.target_ref, &fetched.head_ref)?;
+            debug!(
+                "Diffing {} against merge base {}",
+                fetched.head_ref, merge_base
+            );
+            let merge_base = merge_base.to_string();
+            let mut diff_args = vec!["diff"];
+            if stat {
+                diff_args.push("--stat");
+            }
+            diff_args.push(&merge_base);
+            diff_args.push(&fetched.head_ref);
+            cmd("git", diff_args)
+                .run()
+                .map(|_| ())
+                .map_err(|err| anyhow!("Could not show the diff: {}", err))
+        });
+    if let Err(err) = delete_hidden_refs(&prefix) {
+        warn!("Couldn't clean up the fetched refs: {}", err);
+    }
+    result
+}
+
+/// Fetch a request's head and target branch, then read the request's changes to the file along
+/// with the head commit they were read from. The fetched refs are deleted afterwards.
+pub fn read_request_file_diff(
+    remote_name: &str,
+    mr_id: i64,
+    remote_head_ref: &str,
+    target_branch: &str,
+    path: &str,
+) -> Result<(String, String)> {
+    let prefix = format!("comment/{}", mr_id);
+    let result = fetch_request(remote_name, mr_id, &prefix, remote_head_ref, target_branch)
+        .and_then(|fetched| {
+            let merge_base = get_merge_base(&fetched.target_ref, &fetched.head_ref)?.to_string();
+            let head_sha = resolve_commit(&fetched.head_ref)?;
+            // The prefixes are set explicitly since the user's config can change them
+            let diff = cmd!(
+                "git",
+                "diff",
+                "--no-color",
+                "--no-ext-diff",
+                "--src-prefix=a/",
+                "--dst-prefix=b/",
+                &merge_base,
+                &head_sha,
+                "--",
+                path
+            )
+            .read()
+            .map_err(|err| anyhow!("Could not read the diff: {}", err))?;
+            Ok((head_sha, diff))
+        });
+    if let Err(err) = delete_hidden_refs(&prefix) {
+        warn!("Couldn't clean up the fetched refs: {}", err);
+    }
+    result
+}
+
+/// Fetch a request's head and target branch into hidden refs beneath the prefix, then list the
+/// request's own commits, oldest first. Merges from the target are left out. The fetched refs are
+/// kept so that the commits stay reachable.
+pub fn fetch_request_commits(
+    remote_name: &str,
+    mr_id: i64,
+    prefix: &str,
+    remote_head_ref: &str,
+    target_branch: &str,
+) -> Result<Vec<String>> {
+    let fetched = fetch_request(remote_name, mr_id, prefix, remote_head_ref, target_branch)?;
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(repo.refname_to_id(&fetched.head_ref)?)?;
+    revwalk.hide(repo.refname_to_id(&fetched.target_ref)?)?;
+    revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+    let mut commits = vec![];
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        if commit.parent_count() > 1 {
+            trace!("Skipping merge commit {}", commit.id());
+            continue;
+        }
+        commits.push(commit.id().to_string());
+    }
+    Ok(commits)
+}
+
+/// Cherry-pick the commit onto the current branch, noting where it was picked from. Merge commits
+/// are picked relative to their first parent.
+pub fn cherry_pick(sha: &str) -> Result<()> {
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    let commit = repo.revparse_single(sha)?.peel_to_commit()?;
+    let mut args = vec!["cherry-pick", "-x"];
+    if commit.parent_count() > 1 {
+        args.extend(["-m", "1"]);
+    }
+    args.push(sha);
+    match cmd("git", args).run() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(anyhow!("Could not cherry-pick {}", sha)),
+    }
+}
+
+/// Determine if a cherry-pick stopped for conflicts and hasn't been committed or aborted
+pub fn is_cherry_pick_in_progress() -> bool {
+    get_git_dir().join("CHERRY_PICK_HEAD").exists()
+}
+
+/// Commit a cherry-pick whose conflicts have been resolved, keeping its prepared message
+pub fn continue_cherry_pick() -> Result<()> {
+    // A no-op editor accepts the message while still cleaning it up as an edited one would be
+    match cmd!("git", "-c", "core.editor=true", "cherry-pick", "--continue").run() {
+        Ok(_) => Ok(()),
+        // Git explains what's still unresolved
+        Err(_) => Err(anyhow!("Could not commit the cherry-pick")),
+    }
+}
+
+/// Determine if HEAD is a cherry-pick of the given commit, going by the line that `-x` adds to its
+/// message
+pub fn is_head_picked_from(sha: &str) -> bool {
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    let Ok(head) = repo.head().and_then(|head| head.peel_to_commit()) else {
+        return false;
+    };
+    let line = format!("(cherry picked from commit {})", sha);
+    head.message()
+        .is_some_and(|message| message.contains(&line))
+}
+
+/// Drop the changes of a cherry-pick that stopped for conflicts, leaving it out
+pub fn skip_cherry_pick() -> Result<()> {
+    match cmd!("git", "cherry-pick", "--skip").run() {
+        Ok(_) => Ok(()),
+        Err(err) => Err(anyhow!("Could not skip the cherry-pick: {}", err)),
+    }
+}
+
+/// Abandon a cherry-pick that stopped for conflicts
+pub fn abort_cherry_pick() -> Result<()> {
+    match cmd!("git", "cherry-pick", "--abort").run() {
+        Ok(_) => Ok(()),
+        Err(err) => Err(anyhow!("Could not abort the cherry-pick: {}", err)),
+    }
+}
+
+/// Show how the commits in one range differ from those in another, through git
+fn range_diff(old_range: &str, new_range: &str) -> Result<()> {
+    debug!("Comparing {} to {}", old_range, new_range);
+    cmd!("git", "range-diff", old_range, new_range)
+        .run()
+        .map(|_| ())
+        .map_err(|err| anyhow!("Could not compare the revisions: {}", err))
+}
+
+/// Fetch a request, then show how its latest revision differs from the revision seen before it.
+/// Both revisions are compared against the current target branch.
+pub fn interdiff_request(
+    remote_name: &str,
+    mr_id: i64,
+    remote_head_ref: &str,
+    target_branch: &str,
+) -> Result<()> {
+    let prefix = format!("interdiff/{}", mr_id);
+    let result = fetch_request(remote_name, mr_id, &prefix, remote_head_ref, target_branch)
+        .and_then(|fetched| {
+            let repo = Repository::open_from_env().expect("Couldn't find repository");
+            let previous_ref = get_revision_ref_name(mr_id, "previous");
+            if repo.find_reference(&previous_ref).is_err() {
+                return Err(anyhow!(
+                    "No earlier revision of request {} has been seen",
+                    mr_id
+                ));
+            }
+            let latest_ref = get_revision_ref_name(mr_id, "latest");
+            range_diff(
+                &format!("{}..{}", fetched.target_ref, previous_ref),
+                &format!("{}..{}", fetched.target_ref, latest_ref),
+            )
+        });
+    if let Err(err) = delete_hidden_refs(&prefix) {
+        warn!("Couldn't clean up the fetched refs: {}", err);
+    }
+    result
+}
+
+/// Fetch two revisions of a request by their base and head commits, then show how they differ
+pub fn interdiff_revisions(
+    remote_name: &str,
+    mr_id: i64,
+    (old_base, old_head): (&str, &str),
+    (new_base, new_head): (&str, &str),
+) -> Result<()> {
+    let prefix = format!("interdiff/{}", mr_id);
+    let refs: Vec<(&str, String)> = [
+        (old_base, "old-base"),
+        (old_head, "old-head"),
+        (new_base, "new-base"),
+        (new_head, "new-head"),
+    ]
+    .into_iter()
+    .map(|(sha, name)| (sha, get_hidden_ref_name(&format!("{}/{}", prefix, name))))
+    .collect();
+    let refspecs: Vec<(&str, &str)> = refs
+        .iter()
+        .map(|(sha, local_ref)| (*sha, local_ref.as_str()))
+        .collect();
+    let result = fetch_refs(remote_name, &refspecs).and_then(|_| {
+        range_diff(
+            &format!("{}..{}", refs[0].1, refs[1].1),
+            &format!("{}..{}", refs[2].1, refs[3].1),
+        )
+    });
+    if let Err(err) = delete_hidden_refs(&prefix) {
+        warn!("Couldn't clean up the fetched refs: {}", err);
+    }
+    result
+}
+
+/// Get the path of the repository's `.git` directory
+pub fn get_git_dir() -> PathBuf {
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    repo.path().to_path_buf()
+}
+
+/// Get the path of the repository's working tree, if it has one
+pub fn get_workdir() -> Option<PathBuf> {
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    repo.workdir().map(Path::to_path_buf)
+}
+
+/// Get the messages of the commits on HEAD that aren't on the base ref, oldest first
+pub fn get_commit_messages(base_ref: &str) -> Result<Vec<String>> {
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    let base = repo.revparse_single(base_ref)?.peel_to_commit()?;
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push_head()?;
+    revwalk.hide(base.id())?;
+    revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+    let mut messages = vec![];
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        messages.push(String::from(commit.message().unwrap_or_default()));
+    }
+    Ok(messages)
+}
+
+/// Determine if the remote's copy of the branch matches the local one, going by the last fetch
+pub fn is_branch_pushed(remote_name: &str, branch_name: &str) -> bool {
+    let repo = Repository::open_from_env().expect("Couldn't find repository");
+    let remote_ref = format!("refs/remotes/{}/{}", remote_name, branch_name);
+    let local_ref = format!("refs/heads/{}", branch_name);
+    match (
+        repo.refname_to_id(&remote_ref),
+        repo.refname_to_id(&local_ref),
+    ) {
+        (Ok(remote_oid), Ok(local_oid)) => remote_oid == local_oid,
+        _ => false,
+    }
+}
+
+/// Push the branch to the remote, setting it as the branch's upstream
+pub fn push_branch(remote_name: &str, branch_name: &str) -> Result<()> {
+    debug!("Pushing {} to {}", branch_name, remote_name);
+    match cmd!("git", "push", "--set-upstream", remote_name, branch_name).run() {
+        Ok(_) => Ok(()),
+        Err(err) => Err(anyhow!("Could not push '{}': {}", branch_name, err)),
+    }
+}
+
+/// Open the file in the user's configured git editor, waiting for them to finish
+pub fn edit_file(path: &Path) -> Result<()> {
+    let editor = cmd!("git", "var", "GIT_EDITOR")
+        .read()
+        .map_err(|err| anyhow!("Could not find an editor: {}", err))?;
+    trace!("Editing {:?} with {}", path, editor);
+    // Like git, run the editor through the shell since it may include arguments
+    let script = format!("{} \"$@\"", editor);
+    match cmd!("sh", "-c", script, editor, path).run() {
+        Ok(_) => Ok(()),
+        Err(err) => Err(anyhow!("The editor failed: {}", err)),
+    }
+}
+
+/// Let the user edit the text in their editor, using a scratch file in the git directory
+pub fn edit_text(file_name: &str, initial: &str) -> Result<String> {
+    let path = get_git_dir().join(file_name);
+    fs::write(&path, initial)?;
+    edit_file(&path)?;
+    let text = fs::read_to_string(&path)?;
+    trace!("Edited text: {:?}", text);
+    Ok(text)
+}
@@ -1,19 +1,28 @@
-///! GIT REQ!
+//! GIT REQ!
+mod backport;
 mod cli;
+mod create;
+mod diff;
+mod edit;
 mod git;
+mod markdown;
 mod remotes;
+mod stack;
+mod watch;
 
 use anyhow::Result;
 use clap::{Command, CommandFactory, Parser};
 use clap_complete::{generate, Generator};
 use colored::*;
 use git2::ErrorCode;
-use log::{debug, error, info, trace};
+use log::{debug, error, info, trace, warn};
+use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet};
 use std::io::{self, stdin, stdout, Write};
 use std::{env, process};
 use tabwriter::TabWriter;
 
-use cli::Cli;
+use cli::{Cli, Commands};
 
 fn abort(message: &str) -> ! {
     eprintln!("{}", message.red());
@@ -70,9 +79,76 @@ fn checkout_mr(remote_name: &str, mr_id: i64) {
             eprintln!("Already on branch");
         }
     };
+    match remote.get_req(mr_id) {
+        Ok(mr) => {
+            if mr.draft {
+                let message = format!("Request {} is still a draft", mr_id);
+                eprintln!("{}", message.yellow());
+            }
+            // Remember what the request looked like for comparing against later revisions
+            if let Err(error) = git::record_revision(mr_id, &mr.sha) {
+                debug!("Couldn't record the revision: {}", error);
+            }
+        }
+        Err(error) => debug!("Couldn't check the draft status: {}", error),
+    }
     trace!("Done");
 }
 
+/// Check out the request by applying its patches onto a new branch, or print them as an mbox.
+/// Unlike checking out its branch, this works onto any base and doesn't need the request's refs
+/// to be fetchable.
+fn checkout_patches(remote_name: &str, mr_id: i64, onto: Option<String>, to_stdout: bool) {
+    info!("Getting patches for MR: {}", mr_id);
+    let mut remote = get_remote_hard(remote_name, true);
+    debug!("Found remote: {}", remote);
+    let patches = remote.get_req_patches(mr_id).unwrap_or_else(|error| {
+        let message = format!("There was a problem downloading the patches: {}", error);
+        abort(&message);
+    });
+    if to_stdout {
+        print!("{}", patches);
+        return;
+    }
+    if patches.trim().is_empty() {
+        abort(&format!("Request {} has no commits to apply", mr_id));
+    }
+    let base = onto.unwrap_or_else(|| {
+        let mr = remote.get_req(mr_id).unwrap_or_else(|error| {
+            let message = format!("There was a problem querying the request: {}", error);
+            abort(&message);
+        });
+        git::fetch_branch(remote_name, &mr.target_branch).unwrap_or_else(|error| {
+            let message = format!("There was a problem fetching the target branch: {}", error);
+            abort(&message);
+        })
+    });
+    let local_branch_name = remote.get_local_req_branch(mr_id).unwrap_or_else(|error| {
+        let message = format!(
+            "There was a problem ascertaining the branch name: {}",
+            &error
+        );
+        abort(&message);
+    });
+    let branch_name = format!(
+        "{}-patches",
+        git::get_local_branch_name(remote_name, &local_branch_name)
+    );
+    git::apply_patches(&branch_name, &base, &patches).unwrap_or_else(|error| {
+        abort(&error.to_string());
+    });
+    if git::push_current_ref(mr_id).is_err() {
+        trace!("Couldn't update the current ref");
+        eprintln!("{}", "failed to update some git-req metadata".yellow());
+    }
+    println!(
+        "Applied request {}'s patches onto {} as {}",
+        mr_id.to_string().green(),
+        base,
+        branch_name.green()
+    );
+}
+
 /// Clear the API key for the current domain
 fn clear_domain_key(remote_name: &str) {
     trace!("Deleting domain key");
@@ -126,33 +202,1045 @@ fn set_default_remote(remote_name: &str) {
     eprintln!("{}", "New default remote set!".green());
 }
 
+impl From<cli::SortKey> for remotes::SortKey {
+    fn from(key: cli::SortKey) -> Self {
+        match key {
+            cli::SortKey::Created => remotes::SortKey::Created,
+            cli::SortKey::Updated => remotes::SortKey::Updated,
+            cli::SortKey::Id => remotes::SortKey::Id,
+            cli::SortKey::Title => remotes::SortKey::Title,
+        }
+    }
+}
+
+impl From<cli::SortOrder> for remotes::SortOrder {
+    fn from(order: cli::SortOrder) -> Self {
+        match order {
+            cli::SortOrder::Asc => remotes::SortOrder::Asc,
+            cli::SortOrder::Desc => remotes::SortOrder::Desc,
+        }
+    }
+}
+
+impl From<cli::GroupBy> for remotes::GroupBy {
+    fn from(group_by: cli::GroupBy) -> Self {
+        match group_by {
+            cli::GroupBy::Target => remotes::GroupBy::Target,
+            cli::GroupBy::Author => remotes::GroupBy::Author,
+            cli::GroupBy::Label => remotes::GroupBy::Label,
+        }
+    }
+}
+
+impl From<cli::MergeMethod> for remotes::MergeMethod {
+    fn from(method: cli::MergeMethod) -> Self {
+        match method {
+            cli::MergeMethod::Merge => remotes::MergeMethod::Merge,
+            cli::MergeMethod::Squash => remotes::MergeMethod::Squash,
+            cli::MergeMethod::Rebase => remotes::MergeMethod::Rebase,
+        }
+    }
+}
+
+/// Options for narrowing down and arranging the listed requests
+struct ListOptions {
+    drafts: remotes::DraftFilter,
+    sort: Option<remotes::SortKey>,
+    order: remotes::SortOrder,
+    group_by: Option<remotes::GroupBy>,
+    all_remotes: bool,
+    whole_group: bool,
+}
+
+impl ListOptions {
+    fn from_cli(cli: &Cli) -> Self {
+        let drafts = if cli.no_drafts {
+            remotes::DraftFilter::NoDrafts
+        } else if cli.drafts_only {
+            remotes::DraftFilter::DraftsOnly
+        } else {
+            remotes::DraftFilter::All
+        };
+        ListOptions {
+            drafts,
+            sort: cli.sort.map(remotes::SortKey::from),
+            order: cli.order.into(),
+            group_by: cli.group_by.map(remotes::GroupBy::from),
+            all_remotes: cli.all_remotes,
+            whole_group: cli.whole_group,
+        }
+    }
+}
+
+/// Colorize the pipeline status for display
+fn format_pipeline_status(status: remotes::PipelineStatus) -> ColoredString {
+    let label = status.to_string();
+    match status {
+        remotes::PipelineStatus::Passed => label.green(),
+        remotes::PipelineStatus::Failed => label.red(),
+        remotes::PipelineStatus::Running => label.yellow(),
+        remotes::PipelineStatus::None => label.dimmed(),
+    }
+}
+
+/// Colorize the review status for display, calling out requests the user has approved
+fn format_review_status(mr: &remotes::MergeRequest) -> String {
+    let label = mr.review_status.to_string();
+    let status = match mr.review_status {
+        remotes::ReviewStatus::Approved => label.green(),
+        remotes::ReviewStatus::ChangesRequested => label.red(),
+        remotes::ReviewStatus::ReviewRequired => label.yellow(),
+        remotes::ReviewStatus::Unknown => label.dimmed(),
+    };
+    if mr.approved_by_me {
+        format!("{} {}", status, "(approved by me)".cyan())
+    } else {
+        status.to_string()
+    }
+}
+
+/// Describe whether the request has been checked out locally, is the current request, or is
+/// stale. The current request is the one recorded by git-req, provided it's still checked out.
+fn format_local_status(
+    remote_name: &str,
+    remote: &mut dyn remotes::Remote,
+    mr: &remotes::MergeRequest,
+    current_mr_id: Option<i64>,
+) -> String {
+    let local_branch_name =
+        git::get_local_branch_name(remote_name, &remote.get_local_req_branch_from(mr));
+    let status = match git::get_local_branch_status(&local_branch_name, &mr.sha) {
+        Some(status) => status,
+        None => return String::new(),
+    };
+    let is_current = current_mr_id == Some(mr.id)
+        && git::get_current_branch_name().as_deref() == Some(local_branch_name.as_str());
+    match (is_current, status) {
+        (true, git::LocalBranchStatus::UpToDate) => "current".green().bold().to_string(),
+        (true, git::LocalBranchStatus::Behind) => {
+            format!("{} {}", "current".green().bold(), "(behind)".yellow())
+        }
+        (false, git::LocalBranchStatus::UpToDate) => "local".cyan().to_string(),
+        (false, git::LocalBranchStatus::Behind) => {
+            format!("{} {}", "local".cyan(), "(behind)".yellow())
+        }
+    }
+}
+
+/// Format the columns describing a request's state and title. The branch column is left blank
+/// for remotes whose branch names aren't worth showing.
+fn format_request_cells(
+    mr: &remotes::MergeRequest,
+    show_branch: bool,
+    has_useful_branch_names: bool,
+) -> Vec<String> {
+    let mut cells = vec![
+        format_pipeline_status(mr.pipeline_status).to_string(),
+        format_review_status(mr),
+    ];
+    if show_branch && has_useful_branch_names {
+        cells.push(mr.source_branch.green().dimmed().to_string());
+    } else if show_branch {
+        cells.push(String::new());
+    }
+    cells.push(if mr.draft {
+        format!("{} {}", "[draft]".yellow(), mr.title)
+    } else {
+        mr.title.to_string()
+    });
+    cells
+}
+
+/// The requests retrieved from one of the repository's remotes
+struct RemoteRequests<'a> {
+    remote_name: &'a str,
+    remote: &'a mut dyn remotes::Remote,
+    mrs: &'a [remotes::MergeRequest],
+}
+
+/// A request along with its formatted table row
+struct ListedRequest<'a> {
+    mr: &'a remotes::MergeRequest,
+    row: String,
+}
+
+impl Borrow<remotes::MergeRequest> for ListedRequest<'_> {
+    fn borrow(&self) -> &remotes::MergeRequest {
+        self.mr
+    }
+}
+
+/// Print the requests in a table, split into sections if grouping was requested. When watching,
+/// a leading column highlights the requests that changed since the last poll. When listing
+/// several remotes, their requests are merged together and labelled with the remote's name.
+fn print_requests(
+    sources: &mut [RemoteRequests],
+    options: &ListOptions,
+    changes: Option<&HashMap<i64, watch::RequestChange>>,
+) {
+    let show_branch = sources
+        .iter_mut()
+        .any(|source| source.remote.has_useful_branch_names());
+    let current_mr_id = git::get_current_mr_id().ok();
+    let mut listed = vec![];
+    for source in sources.iter_mut() {
+        let has_useful_branch_names = source.remote.has_useful_branch_names();
+        for mr in source.mrs {
+            let mut cells = vec![];
+            if let Some(changes) = changes {
+                cells.push(
+                    changes
+                        .get(&mr.id)
+                        .map(|change| change.to_string().magenta().bold().to_string())
+                        .unwrap_or_default(),
+                );
+            }
+            cells.push(mr.id.to_string().green().to_string());
+            if options.all_remotes {
+                cells.push(source.remote_name.cyan().to_string());
+            }
+            // Requests from elsewhere in the group can't be matched up with local branches
+            if options.whole_group {
+                cells.push(mr.project.cyan().to_string());
+            } else {
+                cells.push(format_local_status(
+                    source.remote_name,
+                    source.remote,
+                    mr,
+                    current_mr_id,
+                ));
+            }
+            cells.extend(format_request_cells(
+                mr,
+                show_branch,
+                has_useful_branch_names,
+            ));
+            listed.push(ListedRequest {
+                mr,
+                row: cells.join("\t"),
+            });
+        }
+    }
+    // Each remote's requests are already sorted, but merging them undoes that
+    if let (true, Some(sort)) = (sources.len() > 1, options.sort) {
+        remotes::sort_reqs(&mut listed, sort, options.order);
+    }
+    let mut tw = TabWriter::new(io::stdout()).padding(4);
+    match options.group_by {
+        Some(group_by) => {
+            for (index, (name, group)) in remotes::group_reqs(&listed, group_by).iter().enumerate()
+            {
+                if index > 0 {
+                    writeln!(&mut tw).unwrap();
+                }
+                writeln!(&mut tw, "{}", name.bold().underline()).unwrap();
+                for listed_req in group {
+                    writeln!(&mut tw, "{}", listed_req.row).unwrap();
+                }
+            }
+        }
+        None => {
+            for listed_req in &listed {
+                writeln!(&mut tw, "{}", listed_req.row).unwrap();
+            }
+        }
+    }
+    tw.flush().unwrap();
+}
+
+/// Filter the requests, fill in their CI and review details, and sort them
+fn prepare_requests(
+    remote: &mut dyn remotes::Remote,
+    mrs: Vec<remotes::MergeRequest>,
+    options: &ListOptions,
+) -> Vec<remotes::MergeRequest> {
+    let mut mrs = remotes::filter_reqs_by_draft(mrs, options.drafts);
+    remote.populate_pipeline_statuses(&mut mrs);
+    remote.populate_approvals(&mut mrs);
+    if let Some(sort) = options.sort {
+        remotes::sort_reqs(&mut mrs, sort, options.order);
+    }
+    mrs
+}
+
+/// Fill in the details of searched requests where the listing needs them: the head of those
+/// checked out locally, for their local status, and every target when grouping by it
+fn fill_listed_req_details(
+    remote_name: &str,
+    remote: &mut dyn remotes::Remote,
+    mrs: &mut [remotes::MergeRequest],
+    options: &ListOptions,
+) {
+    let by_target = options.group_by == Some(remotes::GroupBy::Target);
+    let needed: HashSet<i64> = mrs
+        .iter()
+        .filter(|mr| {
+            let local_branch_name =
+                git::get_local_branch_name(remote_name, &remote.get_local_req_branch_from(mr));
+            by_target || (!options.whole_group && git::has_local_branch(&local_branch_name))
+        })
+        .map(|mr| mr.id)
+        .collect();
+    remote.fill_req_details(mrs, &|mr| needed.contains(&mr.id));
+}
+
+/// Warn that only some of the matching requests were listed, if the remote cut them short
+fn warn_if_truncated(listed: usize, total_count: usize) {
+    if total_count > listed {
+        let message = format!(
+            "Only the first {} of {} matching requests are shown",
+            listed, total_count
+        );
+        eprintln!("{}", message.yellow());
+    }
+}
+
 /// Print the open requests
-fn list_open_requests(remote_name: &str) {
+fn list_open_requests(remote_name: &str, options: &ListOptions) {
     info!("Getting open requests");
     let mut remote = get_remote_hard(remote_name, true);
     debug!("Found remote: {}", remote);
-    let mrs = remote.get_req_names().unwrap_or_else(|error| {
+    let found = if options.whole_group {
+        remote.get_group_req_names()
+    } else {
+        remote.get_req_names().map(remotes::FoundReqs::complete)
+    };
+    let remotes::FoundReqs {
+        reqs: mut mrs,
+        total_count,
+    } = found.unwrap_or_else(|error| {
         let message = format!("There was a problem querying the open reqs: {}", &error);
         abort(&message);
     });
-    let mut tw = TabWriter::new(io::stdout()).padding(4);
+    let found_count = mrs.len();
+    fill_listed_req_details(remote_name, remote.as_mut(), &mut mrs, options);
+    let mrs = prepare_requests(remote.as_mut(), mrs, options);
+    let source = RemoteRequests {
+        remote_name,
+        remote: remote.as_mut(),
+        mrs: &mrs,
+    };
+    print_requests(&mut [source], options, None);
+    warn_if_truncated(found_count, total_count);
+}
+
+/// Print the open requests of every remote in the repository, skipping any that fail
+fn list_all_open_requests(options: &ListOptions) {
+    info!("Getting open requests for all remotes");
+    let mut remote_names: Vec<String> = git::get_remotes().into_iter().collect();
+    remote_names.sort();
+    let mut fetched = vec![];
+    for remote_name in remote_names {
+        // Other remotes may not be forges at all, so there's no asking for their API keys
+        if !remotes::has_stored_api_key(&git::get_remote_url(&remote_name)) {
+            let message = format!("Skipping '{}': no API token is stored for it", remote_name);
+            eprintln!("{}", message.yellow());
+            continue;
+        }
+        let result = get_remote(&remote_name, true).and_then(|mut remote| {
+            let mrs = remote.get_req_names()?;
+            let mrs = prepare_requests(remote.as_mut(), mrs, options);
+            Ok((remote, mrs))
+        });
+        match result {
+            Ok((remote, mrs)) => fetched.push((remote_name, remote, mrs)),
+            Err(error) => {
+                let message = format!(
+                    "There was a problem querying the open reqs for '{}': {}",
+                    remote_name, error
+                );
+                eprintln!("{}", message.yellow());
+            }
+        }
+    }
+    let mut sources: Vec<RemoteRequests> = fetched
+        .iter_mut()
+        .map(|(remote_name, remote, mrs)| RemoteRequests {
+            remote_name,
+            remote: remote.as_mut(),
+            mrs,
+        })
+        .collect();
+    print_requests(&mut sources, options, None);
+}
+
+/// Print the open requests matching the search query
+fn search_open_requests(remote_name: &str, query: &str, options: &ListOptions) {
+    info!("Searching open requests for {:?}", query);
+    let mut remote = get_remote_hard(remote_name, true);
+    debug!("Found remote: {}", remote);
+    let found = remote.search_reqs(query).unwrap_or_else(|error| {
+        warn!(
+            "Remote search failed, falling back to local search: {}",
+            error
+        );
+        let mrs = remote.get_req_names().unwrap_or_else(|error| {
+            let message = format!("There was a problem querying the open reqs: {}", &error);
+            abort(&message);
+        });
+        remotes::FoundReqs::complete(remotes::filter_reqs_by_text(mrs, query))
+    });
+    let remotes::FoundReqs {
+        reqs: mut mrs,
+        total_count,
+    } = found;
+    let found_count = mrs.len();
+    fill_listed_req_details(remote_name, remote.as_mut(), &mut mrs, options);
+    let mrs = prepare_requests(remote.as_mut(), mrs, options);
+    if mrs.is_empty() {
+        eprintln!("{}", "No matching requests found".yellow());
+        return;
+    }
+    let source = RemoteRequests {
+        remote_name,
+        remote: remote.as_mut(),
+        mrs: &mrs,
+    };
+    print_requests(&mut [source], options, None);
+    warn_if_truncated(found_count, total_count);
+}
+
+/// Format a list of names, or a placeholder if there are none
+fn format_names(names: &[String]) -> String {
+    if names.is_empty() {
+        return "none".dimmed().to_string();
+    }
+    names.join(", ")
+}
+
+/// Format a request's ID and title as a heading
+fn format_request_heading(mr: &remotes::MergeRequest) -> String {
+    let title = if mr.draft {
+        format!("{} {}", "[draft]".yellow(), mr.title.bold())
+    } else {
+        mr.title.bold().to_string()
+    };
+    format!("{} {}", mr.id.to_string().green(), title)
+}
+
+/// Print a request's heading, along with its state, review, and CI status
+fn print_request_summary(mr: &remotes::MergeRequest) {
+    println!("{}", format_request_heading(mr));
+    let mut tw = TabWriter::new(io::stdout()).padding(2);
+    writeln!(&mut tw, "State:\t{}", mr.state).unwrap();
+    writeln!(&mut tw, "Review:\t{}", format_review_status(mr)).unwrap();
+    writeln!(
+        &mut tw,
+        "CI:\t{}",
+        format_pipeline_status(mr.pipeline_status)
+    )
+    .unwrap();
+    writeln!(&mut tw, "URL:\t{}", mr.web_url.underline()).unwrap();
+    tw.flush().unwrap();
+}
+
+/// Print a request's details, followed by its description
+fn print_request_details(mr: &remotes::MergeRequest) {
+    println!("{}", format_request_heading(mr));
+    let mut tw = TabWriter::new(io::stdout()).padding(2);
+    writeln!(&mut tw, "Author:\t{}", mr.author).unwrap();
+    writeln!(&mut tw, "State:\t{}", mr.state).unwrap();
+    writeln!(
+        &mut tw,
+        "Branches:\t{} → {}",
+        mr.source_branch.green(),
+        mr.target_branch.green()
+    )
+    .unwrap();
+    writeln!(&mut tw, "Labels:\t{}", format_names(&mr.labels)).unwrap();
+    writeln!(&mut tw, "Reviewers:\t{}", format_names(&mr.reviewers)).unwrap();
+    writeln!(&mut tw, "Assignees:\t{}", format_names(&mr.assignees)).unwrap();
+    if let Some(milestone) = &mr.milestone {
+        writeln!(&mut tw, "Milestone:\t{}", milestone).unwrap();
+    }
+    writeln!(&mut tw, "Review:\t{}", format_review_status(mr)).unwrap();
+    if !mr.approved_by.is_empty() {
+        writeln!(&mut tw, "Approved by:\t{}", format_names(&mr.approved_by)).unwrap();
+    }
+    writeln!(
+        &mut tw,
+        "CI:\t{}",
+        format_pipeline_status(mr.pipeline_status)
+    )
+    .unwrap();
+    writeln!(&mut tw, "URL:\t{}", mr.web_url.underline()).unwrap();
+    tw.flush().unwrap();
+    if let Some(description) = mr
+        .description
+        .as_deref()
+        .filter(|desc| !desc.trim().is_empty())
+    {
+        println!("\n{}", markdown::render(description));
+    }
+}
+
+/// Print a request's details without checking it out
+fn show_request(remote_name: &str, mr_id: i64, as_json: bool) {
+    info!("Showing MR: {}", mr_id);
+    let mut remote = get_remote_hard(remote_name, true);
+    debug!("Found remote: {}", remote);
+    let mr = remote.get_req(mr_id).unwrap_or_else(|error| {
+        let message = format!("There was a problem querying the request: {}", &error);
+        abort(&message);
+    });
+    let mut mrs = [mr];
+    remote.populate_pipeline_statuses(&mut mrs);
+    remote.populate_approvals(&mut mrs);
+    let [mr] = mrs;
+    if as_json {
+        println!("{}", serde_json::to_string_pretty(&mr).unwrap());
+    } else {
+        print_request_details(&mr);
+    }
+}
+
+/// Find the open requests for the current branch. Branches the user pushed are matched by name,
+/// while those checked out by git-req are matched by their local name or, failing that, their
+/// head commit.
+fn find_current_requests(
+    remote_name: &str,
+    remote: &mut dyn remotes::Remote,
+) -> Result<Vec<remotes::MergeRequest>> {
+    let branch = git::get_current_branch_name();
+    let mut source_branches = vec![];
+    source_branches.extend(git::get_current_upstream_branch_name(remote_name));
+    source_branches.extend(branch.clone());
+    source_branches.dedup();
+    for source_branch in &source_branches {
+        let mrs = remote.find_reqs_by_source_branch(source_branch)?;
+        if !mrs.is_empty() {
+            debug!("Found requests with source branch {}", source_branch);
+            return Ok(mrs);
+        }
+    }
+    let head_sha = git::get_head_sha();
+    let mrs = remote.get_req_names()?;
+    Ok(mrs
+        .into_iter()
+        .filter(|mr| {
+            let local_branch_name =
+                git::get_local_branch_name(remote_name, &remote.get_local_req_branch_from(mr));
+            branch.as_deref() == Some(local_branch_name.as_str())
+                || head_sha.as_deref() == Some(mr.sha.as_str())
+        })
+        .collect())
+}
+
+/// Print the status of the open requests for the current branch
+fn show_current_requests(remote_name: &str) {
+    info!("Finding the requests for the current branch");
+    let mut remote = get_remote_hard(remote_name, true);
+    debug!("Found remote: {}", remote);
+    let mut mrs = find_current_requests(remote_name, remote.as_mut()).unwrap_or_else(|error| {
+        let message = format!("There was a problem querying the open reqs: {}", &error);
+        abort(&message);
+    });
+    if mrs.is_empty() {
+        abort("No open requests found for the current branch");
+    }
+    remote.populate_pipeline_statuses(&mut mrs);
+    remote.populate_approvals(&mut mrs);
+    for (index, mr) in mrs.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        print_request_summary(mr);
+    }
+}
+
+/// Get the ID of the given request, or of the open request for the current branch if none was
+/// given
+fn get_request_id_or_current(
+    remote_name: &str,
+    remote: &mut dyn remotes::Remote,
+    request_id: Option<String>,
+) -> i64 {
+    if let Some(request_id) = request_id {
+        return parse_request_id(&request_id);
+    }
+    let mrs = find_current_requests(remote_name, remote).unwrap_or_else(|error| {
+        let message = format!("There was a problem querying the open reqs: {}", &error);
+        abort(&message);
+    });
+    match mrs.as_slice() {
+        [mr] => mr.id,
+        [] => abort("No open requests found for the current branch"),
+        _ => abort("Several open requests found for the current branch; pass a request ID"),
+    }
+}
+
+/// A verdict to give on a request
+enum ReviewAction {
+    Approve,
+    Unapprove,
+    RequestChanges(String),
+}
+
+/// Give a verdict on the given request, or the one for the current branch
+fn review_request(remote_name: &str, request_id: Option<String>, action: ReviewAction) {
+    let mut remote = get_remote_hard(remote_name, true);
+    debug!("Found remote: {}", remote);
+    let mr_id = get_request_id_or_current(remote_name, remote.as_mut(), request_id);
+    info!("Reviewing MR: {}", mr_id);
+    let (result, done) = match &action {
+        ReviewAction::Approve => (remote.approve_req(mr_id), "Approved"),
+        ReviewAction::Unapprove => (remote.unapprove_req(mr_id), "Withdrew approval of"),
+        ReviewAction::RequestChanges(message) => (
+            remote.request_changes(mr_id, message),
+            "Requested changes to",
+        ),
+    };
+    if let Err(error) = result {
+        let message = format!("There was a problem reviewing the request: {}", error);
+        abort(&message);
+    }
+    println!("{} request {}", done, mr_id.to_string().green());
+}
+
+/// Ask the user a yes/no question, defaulting to no
+fn confirm(question: &str) -> bool {
+    print!("{} [y/N] ", question);
+    let _ = stdout().flush();
+    let mut answer = String::new();
+    if stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Apply the changes to the given request, or the one for the current branch
+fn update_request(
+    remote_name: &str,
+    request_id: Option<String>,
+    update: &remotes::ReqUpdate,
+    done: &str,
+) {
+    let mut remote = get_remote_hard(remote_name, true);
+    debug!("Found remote: {}", remote);
+    let mr_id = get_request_id_or_current(remote_name, remote.as_mut(), request_id);
+    info!("Updating MR {}: {:?}", mr_id, update);
+    let mr = remote.update_req(mr_id, update).unwrap_or_else(|error| {
+        let message = format!("There was a problem updating the request: {}", error);
+        abort(&message);
+    });
+    println!("{} request {}", done, mr.id.to_string().green());
+}
+
+/// Convert the given request, or the one for the current branch, to a draft or mark it as ready
+/// for review
+fn set_request_draft(remote_name: &str, request_id: Option<String>, draft: bool) {
+    let mut remote = get_remote_hard(remote_name, true);
+    debug!("Found remote: {}", remote);
+    let mr_id = get_request_id_or_current(remote_name, remote.as_mut(), request_id);
+    let mr = remote.get_req(mr_id).unwrap_or_else(|error| {
+        let message = format!("There was a problem querying the request: {}", &error);
+        abort(&message);
+    });
+    let state = if draft { "a draft" } else { "ready for review" };
+    if mr.draft == draft {
+        println!("Request {} is already {}", mr_id.to_string().green(), state);
+        return;
+    }
+    info!("Marking MR {} as {}", mr_id, state);
+    let update = remotes::ReqUpdate {
+        draft: Some(draft),
+        ..remotes::ReqUpdate::default()
+    };
+    remote.update_req(mr_id, &update).unwrap_or_else(|error| {
+        let message = format!("There was a problem updating the request: {}", error);
+        abort(&message);
+    });
+    println!("Request {} is now {}", mr_id.to_string().green(), state);
+}
+
+/// Which open requests to retarget from one branch to another
+struct RetargetOptions {
+    from: String,
+    to: String,
+    author: Option<String>,
+    label: Option<String>,
+    dry_run: bool,
+    yes: bool,
+}
+
+/// Move the matching open requests from one target branch to another, after confirming
+fn retarget_requests(remote_name: &str, options: &RetargetOptions) {
+    info!(
+        "Retargeting requests from {} to {}",
+        options.from, options.to
+    );
+    let mut remote = get_remote_hard(remote_name, true);
+    debug!("Found remote: {}", remote);
+    let mrs = remote
+        .find_reqs_by_target_branch(&options.from)
+        .unwrap_or_else(|error| {
+            let message = format!("There was a problem querying the open reqs: {}", &error);
+            abort(&message);
+        });
+    let mrs: Vec<remotes::MergeRequest> = mrs
+        .into_iter()
+        .filter(|mr| {
+            options
+                .author
+                .as_ref()
+                .is_none_or(|author| &mr.author == author)
+                && options
+                    .label
+                    .as_ref()
+                    .is_none_or(|label| mr.labels.contains(label))
+        })
+        .collect();
+    if mrs.is_empty() {
+        println!("No open requests target {}", options.from);
+        return;
+    }
+    let mut tw = TabWriter::new(io::stdout()).padding(2);
     for mr in &mrs {
-        if remote.has_useful_branch_names() {
-            writeln!(
-                &mut tw,
-                "{}\t{}\t{}",
-                mr.id.to_string().green(),
-                mr.source_branch.green().dimmed(),
-                mr.title
-            )
-            .unwrap();
-        } else {
-            writeln!(&mut tw, "{}\t{}", mr.id.to_string().green(), mr.title).unwrap();
+        writeln!(
+            &mut tw,
+            "{}\t{}\t{}",
+            mr.id.to_string().green(),
+            mr.title,
+            mr.author
+        )
+        .unwrap();
+    }
+    tw.flush().unwrap();
+    let summary = format!(
+        "{} request(s) from {} to {}",
+        mrs.len(),
+        options.from,
+        options.to
+    );
+    if options.dry_run {
+        println!("Would retarget {}", summary);
+        return;
+    }
+    if !options.yes && !confirm(&format!("Retarget {}?", summary)) {
+        abort("Not retargeting");
+    }
+    let update = remotes::ReqUpdate {
+        target_branch: Some(options.to.clone()),
+        ..remotes::ReqUpdate::default()
+    };
+    let mut failures = 0;
+    for mr in &mrs {
+        match remote.update_req(mr.id, &update) {
+            Ok(_) => println!("Retargeted request {}", mr.id.to_string().green()),
+            Err(error) => {
+                failures += 1;
+                let message = format!("There was a problem retargeting {}: {}", mr.id, error);
+                eprintln!("{}", message.yellow());
+            }
+        }
+    }
+    if failures > 0 {
+        let message = format!(
+            "{} of {} request(s) weren't retargeted",
+            failures,
+            mrs.len()
+        );
+        abort(&message);
+    }
+}
+
+/// Merge the given request, or the one for the current branch. The merge is refused if the local
+/// checkout of the request has commits that were never pushed, since the remote would merge
+/// something other than what was seen locally.
+fn merge_request(remote_name: &str, request_id: Option<String>, options: &remotes::MergeOptions) {
+    let mut remote = get_remote_hard(remote_name, true);
+    debug!("Found remote: {}", remote);
+    let mr_id = get_request_id_or_current(remote_name, remote.as_mut(), request_id);
+    info!("Merging MR: {}", mr_id);
+    let mr = remote.get_req(mr_id).unwrap_or_else(|error| {
+        let message = format!("There was a problem querying the request: {}", &error);
+        abort(&message);
+    });
+    if mr.state != "open" {
+        let message = format!("Request {} is {}", mr_id, mr.state);
+        abort(&message);
+    }
+    let local_branch_name =
+        git::get_local_branch_name(remote_name, &remote.get_local_req_branch_from(&mr));
+    let head_ref = remote.get_remote_req_head_ref(mr_id);
+    let comparison =
+        git::compare_branch_to_request(remote_name, mr_id, &local_branch_name, &head_ref)
+            .unwrap_or_else(|error| {
+                let message = format!(
+                    "Not merging: couldn't compare the local branch {} to request {}: {}",
+                    local_branch_name, mr_id, error
+                );
+                abort(&message);
+            });
+    // Only what was reviewed locally gets merged, so the merge fails if the request moves on
+    let expected_sha = match comparison {
+        Some(comparison) if comparison.unpushed > 0 => {
+            let message = format!(
+                "Not merging: the local branch {} has {} commit(s) that request {} doesn't",
+                local_branch_name, comparison.unpushed, mr_id
+            );
+            abort(&message);
+        }
+        Some(comparison) if comparison.unreviewed > 0 => {
+            let message = format!(
+                "Not merging: request {} has {} commit(s) that the local branch {} doesn't",
+                mr_id, comparison.unreviewed, local_branch_name
+            );
+            abort(&message);
         }
+        Some(comparison) => comparison.tip,
+        None => mr.sha.clone(),
+    };
+    let outcome = remote
+        .merge_req(mr_id, &expected_sha, options)
+        .unwrap_or_else(|error| {
+            let message = format!("There was a problem merging the request: {}", error);
+            abort(&message);
+        });
+    match outcome {
+        remotes::MergeOutcome::Merged => {
+            println!("Merged request {}", mr_id.to_string().green())
+        }
+        remotes::MergeOutcome::Scheduled => println!(
+            "Request {} will be merged once its requirements are met",
+            mr_id.to_string().green()
+        ),
+    }
+}
+
+/// Find where the line of the file falls within the request's diff, along with the head commit
+/// the diff was read from
+fn locate_request_line(
+    remote_name: &str,
+    remote: &mut dyn remotes::Remote,
+    mr_id: i64,
+    (path, line): (&str, i64),
+) -> (String, remotes::DiffPosition) {
+    let mr = remote.get_req(mr_id).unwrap_or_else(|error| {
+        let message = format!("There was a problem querying the request: {}", &error);
+        abort(&message);
+    });
+    let head_ref = remote.get_remote_req_head_ref(mr_id);
+    let (head_sha, file_diff) =
+        git::read_request_file_diff(remote_name, mr_id, &head_ref, &mr.target_branch, path)
+            .unwrap_or_else(|error| {
+                let message = format!("There was a problem diffing the request: {}", error);
+                abort(&message);
+            });
+    let position = diff::find_line_position(&file_diff, line).unwrap_or_else(|error| {
+        let message = format!("Can't comment on {}:{}: {}", path, line, error);
+        abort(&message);
+    });
+    debug!(
+        "Found {}:{} at {:?} as of {}",
+        path, line, position, head_sha
+    );
+    (head_sha, position)
+}
+
+/// Leave a comment on the given request, or the one for the current branch, optionally on a line
+/// of its diff. Without a message, the comment is written in the user's editor.
+fn comment_on_request(
+    remote_name: &str,
+    request_id: Option<String>,
+    message: Option<String>,
+    location: Option<(&str, i64)>,
+) {
+    let mut remote = get_remote_hard(remote_name, true);
+    debug!("Found remote: {}", remote);
+    let mr_id = get_request_id_or_current(remote_name, remote.as_mut(), request_id);
+    info!("Commenting on MR: {}", mr_id);
+    // Check the line can be commented on before the comment is written
+    let inline =
+        location.map(|location| locate_request_line(remote_name, remote.as_mut(), mr_id, location));
+    let body = match message {
+        Some(message) => message,
+        None => git::edit_text("REQ_COMMENT", "").unwrap_or_else(|error| {
+            let message = format!("Not commenting: {}", error);
+            abort(&message);
+        }),
+    };
+    let body = body.trim();
+    if body.is_empty() {
+        abort("Not commenting: the comment is empty");
+    }
+    let result = match &inline {
+        Some((head_sha, position)) => remote.create_inline_comment(mr_id, head_sha, position, body),
+        None => remote.create_comment(mr_id, body),
+    };
+    let comment = result.unwrap_or_else(|error| {
+        let message = format!("There was a problem commenting on the request: {}", error);
+        abort(&message);
+    });
+    debug!("Created comment {}", comment.id);
+    match inline {
+        Some((_, position)) => println!(
+            "Commented on {}:{} of request {}",
+            position.new_path,
+            position.new_line,
+            mr_id.to_string().green()
+        ),
+        None => println!("Commented on request {}", mr_id.to_string().green()),
+    }
+}
+
+/// Print a discussion thread, with the end of the diff hunk it's attached to
+fn print_discussion(discussion: &remotes::Discussion) {
+    let heading = match &discussion.location {
+        Some(location) => match location.line {
+            Some(line) => format!("{}:{}", location.path, line),
+            None => location.path.clone(),
+        },
+        None => String::from("General"),
+    };
+    let state = match discussion.resolved {
+        Some(true) => format!(" {}", "[resolved]".green()),
+        Some(false) => format!(" {}", "[unresolved]".yellow()),
+        None => String::new(),
+    };
+    println!("{}{}", heading.bold(), state);
+    if let Some(hunk) = discussion
+        .location
+        .as_ref()
+        .and_then(|location| location.diff_hunk.as_deref())
+    {
+        // The commented line is the hunk's last, so a few lines before it are enough context
+        let lines: Vec<&str> = hunk.lines().collect();
+        for line in &lines[lines.len().saturating_sub(4)..] {
+            println!("  {}", line.dimmed());
+        }
+    }
+    for comment in &discussion.comments {
+        println!(
+            "  {} {}",
+            comment.author.cyan(),
+            comment.created_at.dimmed()
+        );
+        for line in markdown::render(&comment.body).lines() {
+            println!("    {}", line);
+        }
+    }
+}
+
+/// Print the discussion threads on the given request, or the one for the current branch
+fn show_discussions(remote_name: &str, request_id: Option<String>, as_json: bool) {
+    let mut remote = get_remote_hard(remote_name, true);
+    debug!("Found remote: {}", remote);
+    let mr_id = get_request_id_or_current(remote_name, remote.as_mut(), request_id);
+    info!("Showing discussions on MR: {}", mr_id);
+    let discussions = remote.get_discussions(mr_id).unwrap_or_else(|error| {
+        let message = format!("There was a problem querying the discussions: {}", error);
+        abort(&message);
+    });
+    if as_json {
+        println!("{}", serde_json::to_string_pretty(&discussions).unwrap());
+        return;
+    }
+    if discussions.is_empty() {
+        println!("No comments on request {}", mr_id.to_string().green());
+    }
+    for (index, discussion) in discussions.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        print_discussion(discussion);
+    }
+}
+
+/// Find the commit that last changed the line at a `file:line` location
+fn blame_location(location: &str) -> String {
+    let (path, line) = location
+        .rsplit_once(':')
+        .and_then(|(path, line)| Some((path, line.parse::<usize>().ok()?)))
+        .unwrap_or_else(|| {
+            abort("Expected the location as FILE:LINE");
+        });
+    let sha = git::blame_line(path, line).unwrap_or_else(|error| {
+        abort(&error.to_string());
+    });
+    trace!("Line {} of {} was last changed by {}", line, path, sha);
+    sha
+}
+
+/// Print the requests, open or not, that contain the commit
+fn show_commit_requests(remote_name: &str, commit: &str) {
+    // Expand abbreviated SHAs and other revisions, if the commit is available locally
+    let sha = git::resolve_commit(commit).unwrap_or_else(|_| String::from(commit));
+    info!("Finding the requests containing {}", sha);
+    let mut remote = get_remote_hard(remote_name, true);
+    debug!("Found remote: {}", remote);
+    let mrs = remote.find_reqs_by_commit(&sha).unwrap_or_else(|error| {
+        let message = format!("There was a problem querying the requests: {}", &error);
+        abort(&message);
+    });
+    if mrs.is_empty() {
+        abort("No requests found containing the commit");
+    }
+    let mut tw = TabWriter::new(io::stdout()).padding(4);
+    for mr in &mrs {
+        writeln!(
+            &mut tw,
+            "{}\t{}\t{}\t{}",
+            mr.id.to_string().green(),
+            mr.state,
+            mr.title,
+            mr.web_url.underline()
+        )
+        .unwrap();
     }
     tw.flush().unwrap();
 }
 
+/// Print the changes a request makes to its target branch without checking it out
+fn diff_request(remote_name: &str, mr_id: i64, stat: bool) {
+    info!("Diffing MR: {}", mr_id);
+    let mut remote = get_remote_hard(remote_name, true);
+    debug!("Found remote: {}", remote);
+    let mr = remote.get_req(mr_id).unwrap_or_else(|error| {
+        let message = format!("There was a problem querying the request: {}", &error);
+        abort(&message);
+    });
+    let head_ref = remote.get_remote_req_head_ref(mr_id);
+    if let Err(error) = git::diff_request(remote_name, mr_id, &head_ref, &mr.target_branch, stat) {
+        let message = format!("There was a problem diffing the request: {}", error);
+        abort(&message);
+    }
+}
+
+/// Show how a force-pushed request changed between revisions, either the ones seen locally or
+/// the ones recorded by the remote
+fn interdiff_request(remote_name: &str, mr_id: i64, server: bool) {
+    info!("Comparing revisions of MR: {}", mr_id);
+    let mut remote = get_remote_hard(remote_name, true);
+    debug!("Found remote: {}", remote);
+    let result = if server {
+        let revisions = remote.get_req_revisions(mr_id).unwrap_or_else(|error| {
+            let message = format!("There was a problem querying the revisions: {}", &error);
+            abort(&message);
+        });
+        match revisions.as_slice() {
+            [latest, previous, ..] => git::interdiff_revisions(
+                remote_name,
+                mr_id,
+                (&previous.base_sha, &previous.head_sha),
+                (&latest.base_sha, &latest.head_sha),
+            ),
+            _ => abort("The remote has only recorded one revision of the request"),
+        }
+    } else {
+        let mr = remote.get_req(mr_id).unwrap_or_else(|error| {
+            let message = format!("There was a problem querying the request: {}", &error);
+            abort(&message);
+        });
+        let head_ref = remote.get_remote_req_head_ref(mr_id);
+        git::interdiff_request(remote_name, mr_id, &head_ref, &mr.target_branch)
+    };
+    if let Err(error) = result {
+        let message = format!("There was a problem comparing the revisions: {}", error);
+        abort(&message);
+    }
+}
+
 fn print_completions<G: Generator>(generator: G, cmd: &mut Command) {
     generate(
         generator,
@@ -190,6 +1278,21 @@ fn get_remote_name(remote_override: Option<String>) -> String {
     remote_override.unwrap_or(default_remote_name)
 }
 
+/// Parse a request ID from the command line, resolving '-' to the previously checked-out request
+fn parse_request_id(request_id: &str) -> i64 {
+    if request_id == "-" {
+        trace!("Received request for previous MR");
+        git::get_previous_mr_id().unwrap_or_else(|_| {
+            abort("Could not find previous request");
+        })
+    } else {
+        trace!("Received request for numbered MR: {}", request_id);
+        request_id.parse::<i64>().unwrap_or_else(|_| {
+            abort("Invalid request ID provided");
+        })
+    }
+}
+
 /// Do the thing
 fn main() {
     color_backtrace::install();
@@ -198,13 +1301,186 @@ fn main() {
         .try_init();
 
     let cli = Cli::parse();
+    let list_options = ListOptions::from_cli(&cli);
 
-    if let Some(project_id) = cli.new_project_id {
+    if let Some(command) = cli.command {
+        let remote_name = get_remote_name(cli.remote_name);
+        match command {
+            Commands::Show { request_id, json } => {
+                show_request(&remote_name, parse_request_id(&request_id), json)
+            }
+            Commands::Diff { request_id, stat } => {
+                diff_request(&remote_name, parse_request_id(&request_id), stat)
+            }
+            Commands::Interdiff { request_id, server } => {
+                interdiff_request(&remote_name, parse_request_id(&request_id), server)
+            }
+            Commands::Status => show_current_requests(&remote_name),
+            Commands::Approve { request_id } => {
+                review_request(&remote_name, request_id, ReviewAction::Approve)
+            }
+            Commands::Unapprove { request_id } => {
+                review_request(&remote_name, request_id, ReviewAction::Unapprove)
+            }
+            Commands::RequestChanges {
+                request_id,
+                message,
+            } => review_request(
+                &remote_name,
+                request_id,
+                ReviewAction::RequestChanges(message),
+            ),
+            Commands::Comment {
+                request_id,
+                message,
+                file,
+                line,
+            } => {
+                let location = file.as_deref().zip(line);
+                comment_on_request(&remote_name, request_id, message, location)
+            }
+            Commands::Edit {
+                request_id,
+                file,
+                print,
+            } => {
+                let options = edit::EditOptions { file, print };
+                edit::edit_request(&remote_name, request_id, &options)
+            }
+            Commands::Ready { request_id } => set_request_draft(&remote_name, request_id, false),
+            Commands::Draft { request_id } => set_request_draft(&remote_name, request_id, true),
+            Commands::Close { request_id } => {
+                let update = remotes::ReqUpdate {
+                    state: Some(remotes::StateChange::Close),
+                    ..remotes::ReqUpdate::default()
+                };
+                update_request(&remote_name, request_id, &update, "Closed")
+            }
+            Commands::Reopen { request_id } => {
+                let update = remotes::ReqUpdate {
+                    state: Some(remotes::StateChange::Reopen),
+                    ..remotes::ReqUpdate::default()
+                };
+                update_request(&remote_name, request_id, &update, "Reopened")
+            }
+            Commands::Retarget {
+                branch,
+                request_id,
+                from,
+                to,
+                author,
+                label,
+                dry_run,
+                yes,
+            } => match (branch, from.zip(to)) {
+                (_, Some((from, to))) => {
+                    let options = RetargetOptions {
+                        from,
+                        to,
+                        author,
+                        label,
+                        dry_run,
+                        yes,
+                    };
+                    retarget_requests(&remote_name, &options)
+                }
+                (Some(branch), None) => {
+                    let update = remotes::ReqUpdate {
+                        target_branch: Some(branch),
+                        ..remotes::ReqUpdate::default()
+                    };
+                    update_request(&remote_name, request_id, &update, "Retargeted")
+                }
+                (None, None) => abort("A branch to target is required"),
+            },
+            Commands::Merge {
+                request_id,
+                method,
+                auto,
+                delete_branch,
+                message,
+            } => {
+                let options = remotes::MergeOptions {
+                    method: method.into(),
+                    auto,
+                    delete_branch,
+                    message,
+                };
+                merge_request(&remote_name, request_id, &options)
+            }
+            Commands::Stack {
+                request_id,
+                checkout,
+                rebase,
+                no_push,
+            } => {
+                let options = stack::StackOptions {
+                    checkout,
+                    rebase,
+                    push: !no_push,
+                };
+                stack::stack_requests(&remote_name, request_id, &options)
+            }
+            Commands::Backport {
+                request_id,
+                onto,
+                create,
+                resume,
+                skip,
+                abort: abort_flag,
+            } => {
+                if resume {
+                    backport::continue_backport();
+                } else if skip {
+                    backport::skip_backport_commit();
+                } else if abort_flag {
+                    backport::abort_backport();
+                } else {
+                    let options = backport::BackportOptions {
+                        onto: onto.unwrap_or_default(),
+                        create,
+                    };
+                    backport::backport_request(&remote_name, request_id, &options);
+                }
+            }
+            Commands::Comments { request_id, json } => {
+                show_discussions(&remote_name, request_id, json)
+            }
+            Commands::ForCommit { commit, blame } => {
+                let sha = match (commit, blame) {
+                    (_, Some(location)) => blame_location(&location),
+                    (Some(commit), None) => commit,
+                    (None, None) => abort("A commit is required"),
+                };
+                show_commit_requests(&remote_name, &sha)
+            }
+            Commands::Create {
+                target,
+                title,
+                edit,
+                draft,
+            } => {
+                let options = create::CreateOptions {
+                    target,
+                    title,
+                    edit,
+                    draft,
+                };
+                create::create_request(&remote_name, &options)
+            }
+        }
+    } else if let Some(project_id) = cli.new_project_id {
         set_project_id(&get_remote_name(cli.remote_name), &project_id);
     } else if cli.clear_project_id {
         clear_project_id(&get_remote_name(cli.remote_name));
+    } else if let Some(interval) = cli.watch {
+        watch::watch_open_requests(&get_remote_name(cli.remote_name), &list_options, interval);
+    } else if cli.all_remotes {
+        list_all_open_requests(&list_options);
     } else if cli.list {
-        list_open_requests(&get_remote_name(cli.remote_name));
+        list_open_requests(&get_remote_name(cli.remote_name), &list_options);
+    } else if let Some(query) = cli.search {
+        search_open_requests(&get_remote_name(cli.remote_name), &query, &list_options);
     } else if cli.clear_domain_key {
         clear_domain_key(&get_remote_name(cli.remote_name));
     } else if let Some(domain_key) = cli.new_domain_key {
@@ -218,17 +1494,12 @@ fn main() {
         let request_id = cli.request_id.unwrap_or_else(|| {
             abort("Request ID required");
         });
-        let mr_id = if request_id == "-" {
-            trace!("Received request for previous MR");
-            git::get_previous_mr_id().unwrap_or_else(|_| {
-                abort("Could not find previous request");
-            })
+        let remote_name = get_remote_name(cli.remote_name);
+        let mr_id = parse_request_id(&request_id);
+        if cli.as_patches {
+            checkout_patches(&remote_name, mr_id, cli.onto, cli.stdout);
         } else {
-            trace!("Received request for numbered MR: {}", request_id);
-            request_id.parse::<i64>().unwrap_or_else(|_| {
-                abort("Invalid request ID provided");
-            })
-        };
-        checkout_mr(&get_remote_name(cli.remote_name), mr_id);
+            checkout_mr(&remote_name, mr_id);
+        }
     }
 }
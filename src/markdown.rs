@@ -0,0 +1,157 @@
+//! Render Markdown request descriptions for the terminal
+use colored::*;
+
+/// Render the Markdown with terminal styling. Only the common elements are styled; anything else
+/// is passed through as written.
+pub fn render(markdown: &str) -> String {
+    let mut lines = vec![];
+    let mut in_code_block = false;
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            lines.push(format!("    {}", line.dimmed()));
+        } else {
+            lines.push(render_line(line));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Render a line outside of a code block
+fn render_line(line: &str) -> String {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+    if let Some(heading) = get_heading(trimmed) {
+        return heading.bold().underline().to_string();
+    }
+    if is_rule(trimmed) {
+        return "─".repeat(40).dimmed().to_string();
+    }
+    if let Some(quote) = trimmed.strip_prefix('>') {
+        return format!("{}{}", "│ ".dimmed(), render_inline(quote.trim_start()));
+    }
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(item) = trimmed.strip_prefix(bullet) {
+            return format!("{}• {}", indent, render_inline(item));
+        }
+    }
+    render_inline(line)
+}
+
+/// Get the text of an ATX heading (`# Heading`)
+fn get_heading(line: &str) -> Option<&str> {
+    let text = line.trim_start_matches('#');
+    let level = line.len() - text.len();
+    if !(1..=6).contains(&level) || !(text.is_empty() || text.starts_with(' ')) {
+        return None;
+    }
+    Some(text.trim().trim_end_matches('#').trim_end())
+}
+
+/// Determine if the line is a thematic break (`---`, `***`, or `___`)
+fn is_rule(line: &str) -> bool {
+    let marks: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3
+        && ['-', '*', '_']
+            .iter()
+            .any(|mark| marks.chars().all(|c| c == *mark))
+}
+
+/// Style the code spans, emphasis, and links within a line. Unclosed markers are left as-is.
+fn render_inline(text: &str) -> String {
+    let mut rendered = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let styled = match c {
+            '`' => split_delimited(rest, "`").map(|(code, after)| (code.cyan().to_string(), after)),
+            '*' | '_' if rest[1..].starts_with(c) => split_delimited(rest, &rest[..2])
+                .map(|(strong, after)| (strong.bold().to_string(), after)),
+            // Underscores within words (e.g. snake_case) aren't emphasis
+            '*' => split_delimited(rest, "*").map(|(em, after)| (em.italic().to_string(), after)),
+            '_' if !rendered.ends_with(char::is_alphanumeric) => {
+                split_delimited(rest, "_").map(|(em, after)| (em.italic().to_string(), after))
+            }
+            '[' => split_link(rest).map(|(label, url, after)| {
+                (format!("{} ({})", label.underline(), url.dimmed()), after)
+            }),
+            _ => None,
+        };
+        match styled {
+            Some((styled, after)) => {
+                rendered.push_str(&styled);
+                rest = after;
+            }
+            None => {
+                rendered.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    rendered
+}
+
+/// Split off the non-empty text between a leading delimiter and its closing counterpart
+fn split_delimited<'a>(text: &'a str, delimiter: &str) -> Option<(&'a str, &'a str)> {
+    let inner = text.strip_prefix(delimiter)?;
+    let end = inner.find(delimiter)?;
+    if end == 0 {
+        return None;
+    }
+    Some((&inner[..end], &inner[end + delimiter.len()..]))
+}
+
+/// Split off a leading `[label](url)` link
+fn split_link(text: &str) -> Option<(&str, &str, &str)> {
+    let (label, rest) = text.strip_prefix('[')?.split_once("](")?;
+    let (url, after) = rest.split_once(')')?;
+    Some((label, url, after))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_headings() {
+        assert_eq!(
+            format!("{}", "Summary".bold().underline()),
+            render("## Summary ##")
+        );
+        assert_eq!("#hashtag", render("#hashtag"));
+    }
+
+    #[test]
+    fn test_render_lists() {
+        let expected = format!("• {}\n  • nested", "one".bold());
+        assert_eq!(expected, render("- **one**\n  * nested"));
+    }
+
+    #[test]
+    fn test_render_code_block() {
+        let expected = format!("Run:\n    {}", "cargo test".dimmed());
+        assert_eq!(expected, render("Run:\n```sh\ncargo test\n```"));
+    }
+
+    #[test]
+    fn test_render_inline() {
+        let expected = format!(
+            "Call {} in {} ({})",
+            "get_remote".cyan(),
+            "main".underline(),
+            "src/main.rs".dimmed()
+        );
+        assert_eq!(expected, render("Call `get_remote` in [main](src/main.rs)"));
+    }
+
+    #[test]
+    fn test_render_inline_passthrough() {
+        assert_eq!(
+            "Fixes snake_case_names and 2 * 3 ` [",
+            render("Fixes snake_case_names and 2 * 3 ` [")
+        );
+    }
+}
@@ -1,26 +1,151 @@
-use crate::remotes::{MergeRequest, Remote};
+use crate::remotes::{
+    encode_query_value, for_each_req_concurrently, read_json_body, Comment, CommentLocation,
+    DiffPosition, Discussion, FoundReqs, MergeMethod, MergeOptions, MergeOutcome, MergeRequest,
+    NewRequest, PipelineStatus, Remote, ReqUpdate, ResponseCache, ReviewStatus, StateChange,
+};
 use anyhow::{anyhow, Result};
 use log::{debug, trace};
 use regex::Regex;
 use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Debug)]
 pub struct GitHub {
     pub id: String,
     pub domain: String,
+    #[allow(dead_code)]
     pub name: String,
+    #[allow(dead_code)]
     pub origin: String,
     pub api_root: String,
     pub api_key: String,
+    pub cache: ResponseCache,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct GitHubPullRequest {
     id: i64,
+    // The ID used by the GraphQL API
+    node_id: Option<String>,
     number: i64,
     title: String,
     body: Option<String>,
+    state: String,
+    merged_at: Option<String>,
+    // Before merging, this is GitHub's test merge rather than a commit on the base branch
+    merge_commit_sha: Option<String>,
     html_url: String,
+    #[serde(default)]
+    draft: bool,
+    // Absent for PRs opened by deleted accounts
+    user: Option<GitHubUser>,
+    #[serde(default)]
+    labels: Vec<GitHubLabel>,
+    // Absent from search results
+    #[serde(default)]
+    requested_reviewers: Vec<GitHubUser>,
+    #[serde(default)]
+    assignees: Vec<GitHubUser>,
+    milestone: Option<GitHubMilestone>,
+    created_at: String,
+    updated_at: String,
+    // Absent from search results, which are issues rather than PRs
+    head: Option<GitHubBranch>,
+    base: Option<GitHubBranch>,
+    // Only present in search results
+    repository_url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitHubMilestone {
+    number: i64,
+    title: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitHubLabel {
+    name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitHubBranch {
+    #[serde(rename = "ref")]
+    ref_name: String,
+    sha: String,
+    // Absent if the repository has been deleted
+    repo: Option<GitHubRepository>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitHubRepository {
+    full_name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitHubCombinedStatus {
+    state: String,
+    total_count: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitHubCheckRun {
+    status: String,
+    conclusion: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitHubCheckRuns {
+    total_count: i64,
+    check_runs: Vec<GitHubCheckRun>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitHubUser {
+    login: String,
+}
+
+/// The user or organization that owns a repository
+#[derive(Serialize, Deserialize, Debug)]
+struct GitHubOwner {
+    login: String,
+    // Either "User" or "Organization"
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitHubReview {
+    id: i64,
+    // Absent for reviews left by deleted accounts
+    user: Option<GitHubUser>,
+    state: String,
+}
+
+/// An issue comment or a review comment; the diff fields are only present for the latter
+#[derive(Serialize, Deserialize, Debug)]
+struct GitHubComment {
+    id: i64,
+    // Absent for comments left by deleted accounts
+    user: Option<GitHubUser>,
+    body: String,
+    created_at: String,
+    in_reply_to_id: Option<i64>,
+    path: Option<String>,
+    // Null once the line is no longer in the diff
+    line: Option<i64>,
+    diff_hunk: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitHubProject {
+    full_name: String,
+    default_branch: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitHubSearchResults {
+    total_count: i64,
+    items: Vec<GitHubPullRequest>,
 }
 
 impl Remote for GitHub {
@@ -36,18 +161,226 @@ impl Remote for GitHub {
         Ok(format!("pr/{mr_id}", mr_id = mr_id))
     }
 
+    fn get_local_req_branch_from(&mut self, mr: &MergeRequest) -> String {
+        format!("pr/{mr_id}", mr_id = mr.id)
+    }
+
     fn get_remote_req_branch(&mut self, mr_id: i64) -> Result<String> {
         Ok(format!("pull/{mr_id}/head", mr_id = mr_id))
     }
 
+    fn get_remote_req_head_ref(&mut self, mr_id: i64) -> String {
+        format!("refs/pull/{}/head", mr_id)
+    }
+
     fn get_req_names(&mut self) -> Result<Vec<MergeRequest>> {
-        retrieve_github_project_pull_requests(self)
+        retrieve_github_project_pull_requests(self, None, None)
+    }
+
+    fn find_reqs_by_source_branch(&mut self, branch: &str) -> Result<Vec<MergeRequest>> {
+        // Branches from forks are qualified by their owner, so only the remote's own are found
+        let owner = self.id.split('/').next().unwrap_or_default();
+        let head = format!("{}:{}", owner, branch);
+        retrieve_github_project_pull_requests(self, Some(&head), None)
+    }
+
+    fn find_reqs_by_target_branch(&mut self, branch: &str) -> Result<Vec<MergeRequest>> {
+        retrieve_github_pull_requests_by_base(self, branch)
+    }
+
+    fn find_merged_reqs_by_source_branch(&mut self, branch: &str) -> Result<Vec<MergeRequest>> {
+        let owner = self.id.split('/').next().unwrap_or_default();
+        let head = format!("{}:{}", owner, branch);
+        // GitHub counts merged pull requests as closed
+        let prs = retrieve_github_project_pull_requests(self, Some(&head), Some("closed"))?;
+        Ok(prs.into_iter().filter(|pr| pr.state == "merged").collect())
+    }
+
+    fn find_reqs_by_commit(&mut self, sha: &str) -> Result<Vec<MergeRequest>> {
+        retrieve_github_commit_pull_requests(self, sha)
+    }
+
+    fn get_group_req_names(&mut self) -> Result<FoundReqs> {
+        let owner = self.id.split('/').next().unwrap_or_default();
+        // Searching a user's repositories leaves out those of an organization, and vice versa
+        let qualifier = match retrieve_github_owner(self, owner)?.kind.as_str() {
+            "Organization" => "org",
+            _ => "user",
+        };
+        search_github_pull_requests(self, &format!("{}:{}", qualifier, owner), "")
+    }
+
+    fn get_req(&mut self, mr_id: i64) -> Result<MergeRequest> {
+        retrieve_github_pull_request(self, &self.id, mr_id)
+    }
+
+    fn get_default_branch(&mut self) -> Result<String> {
+        Ok(retrieve_github_project(self)?.default_branch)
+    }
+
+    fn create_req(&mut self, new_req: &NewRequest) -> Result<MergeRequest> {
+        create_github_pull_request(self, new_req)
+    }
+
+    fn fill_req_details(
+        &mut self,
+        reqs: &mut [MergeRequest],
+        needed: &(dyn Fn(&MergeRequest) -> bool + Sync),
+    ) {
+        let remote = &*self;
+        for_each_req_concurrently(reqs, |req| {
+            // Search results are issues, which lack the branch details
+            if !req.sha.is_empty() || !needed(req) {
+                return;
+            }
+            match retrieve_github_pull_request(remote, get_github_req_repo(remote, req), req.id) {
+                Ok(full_req) => *req = full_req,
+                Err(err) => debug!("Couldn't get the details for PR {}: {}", req.id, err),
+            }
+        });
+    }
+
+    fn populate_pipeline_statuses(&mut self, reqs: &mut [MergeRequest]) {
+        let remote = &*self;
+        for_each_req_concurrently(reqs, |req| {
+            // Requests found through a search don't know their head, but the PR's ref does
+            let head = match req.sha.as_str() {
+                "" => format!("refs/pull/{}/head", req.id),
+                sha => String::from(sha),
+            };
+            match retrieve_github_commit_status(remote, get_github_req_repo(remote, req), &head) {
+                Ok(status) => req.pipeline_status = status,
+                Err(err) => debug!("Couldn't get the status for PR {}: {}", req.id, err),
+            }
+        });
+    }
+
+    fn populate_approvals(&mut self, reqs: &mut [MergeRequest]) {
+        let username = retrieve_github_username(self)
+            .map_err(|err| debug!("Couldn't get the current user: {}", err))
+            .ok();
+        let remote = &*self;
+        for_each_req_concurrently(reqs, |req| {
+            match retrieve_github_reviews(remote, get_github_req_repo(remote, req), req.id) {
+                Ok(reviews) => {
+                    let (status, approvers) = summarize_github_reviews(&reviews);
+                    req.review_status = status;
+                    req.approved_by_me = username
+                        .as_ref()
+                        .is_some_and(|username| approvers.contains(username));
+                    req.approved_by = approvers;
+                }
+                Err(err) => debug!("Couldn't get the reviews for PR {}: {}", req.id, err),
+            }
+        });
+    }
+
+    fn approve_req(&mut self, mr_id: i64) -> Result<()> {
+        create_github_review(self, mr_id, "APPROVE", "")
+    }
+
+    fn unapprove_req(&mut self, mr_id: i64) -> Result<()> {
+        let username = retrieve_github_username(self)?;
+        let reviews = retrieve_github_reviews(self, &self.id, mr_id)?;
+        match find_github_approval(&reviews, &username) {
+            Some(review_id) => dismiss_github_review(self, mr_id, review_id),
+            None => Err(anyhow!("you haven't approved this pull request")),
+        }
+    }
+
+    fn request_changes(&mut self, mr_id: i64, message: &str) -> Result<()> {
+        create_github_review(self, mr_id, "REQUEST_CHANGES", message)
+    }
+
+    fn update_req(&mut self, mr_id: i64, update: &ReqUpdate) -> Result<MergeRequest> {
+        update_github_pull_request(self, mr_id, update)
+    }
+
+    fn merge_req(
+        &mut self,
+        mr_id: i64,
+        head_sha: &str,
+        options: &MergeOptions,
+    ) -> Result<MergeOutcome> {
+        let pr = query_github_pull_request(self, &self.id, mr_id)?;
+        if options.auto {
+            if options.delete_branch {
+                return Err(anyhow!(
+                    "GitHub can't delete the branch after an auto-merge; turn on the \
+                     repository's setting for deleting head branches instead"
+                ));
+            }
+            let node_id = pr
+                .node_id
+                .ok_or_else(|| anyhow!("the pull request has no GraphQL ID"))?;
+            enable_github_auto_merge(self, &node_id, head_sha, options)?;
+            return Ok(MergeOutcome::Scheduled);
+        }
+        // Branches in forks belong to someone else, so only the project's own can be deleted
+        let own_branch = pr.head.filter(|head| {
+            head.repo
+                .as_ref()
+                .is_some_and(|repo| repo.full_name == self.id)
+        });
+        if options.delete_branch && own_branch.is_none() {
+            return Err(anyhow!("the branch is in a fork, so it can't be deleted"));
+        }
+        merge_github_pull_request(self, mr_id, head_sha, options)?;
+        if let Some(head) = own_branch.filter(|_| options.delete_branch) {
+            delete_github_branch(self, &head.ref_name)?;
+        }
+        Ok(MergeOutcome::Merged)
+    }
+
+    fn get_req_patches(&mut self, mr_id: i64) -> Result<String> {
+        retrieve_github_patches(self, mr_id)
+    }
+
+    fn get_discussions(&mut self, mr_id: i64) -> Result<Vec<Discussion>> {
+        let issue_comments = retrieve_github_comments(self, "issues", mr_id)?;
+        let review_comments = retrieve_github_comments(self, "pulls", mr_id)?;
+        // Resolution is only exposed through GraphQL, so the threads are still worth showing
+        // without it
+        let resolved = retrieve_github_thread_resolutions(self, mr_id).unwrap_or_else(|err| {
+            debug!(
+                "Couldn't get the thread resolutions for PR {}: {}",
+                mr_id, err
+            );
+            HashMap::new()
+        });
+        Ok(group_github_comments(
+            issue_comments,
+            review_comments,
+            &resolved,
+        ))
+    }
+
+    fn create_comment(&mut self, mr_id: i64, body: &str) -> Result<Comment> {
+        create_github_comment(self, mr_id, body)
+    }
+
+    fn create_inline_comment(
+        &mut self,
+        mr_id: i64,
+        head_sha: &str,
+        position: &DiffPosition,
+        body: &str,
+    ) -> Result<Comment> {
+        create_github_review_comment(self, mr_id, head_sha, position, body)
+    }
+
+    fn search_reqs(&mut self, query: &str) -> Result<FoundReqs> {
+        search_github_pull_requests(self, &format!("repo:{}", self.id), query)
     }
 
     fn has_useful_branch_names(&mut self) -> bool {
         false
     }
 
+    fn get_response_cache(&self) -> &ResponseCache {
+        &self.cache
+    }
+
     fn has_virtual_remote_branch_names(&mut self) -> bool {
         true
     }
@@ -55,14 +388,57 @@ impl Remote for GitHub {
 
 /// Convert a GitHub PR to a git-req MergeRequest
 fn github_to_mr(req: GitHubPullRequest) -> MergeRequest {
+    let project = req
+        .base
+        .as_ref()
+        .and_then(|base| base.repo.as_ref())
+        .map(|repo| repo.full_name.clone())
+        .or_else(|| get_github_repo_from_api_url(req.repository_url.as_deref()?))
+        .unwrap_or_default();
     MergeRequest {
         id: req.number,
+        project,
         title: req.title,
         description: req.body,
-        source_branch: format!("pr/{}", req.number),
+        state: if req.merged_at.is_some() {
+            String::from("merged")
+        } else {
+            req.state
+        },
+        source_branch: req
+            .head
+            .as_ref()
+            .map(|head| head.ref_name.clone())
+            .unwrap_or_default(),
+        target_branch: req.base.map(|base| base.ref_name).unwrap_or_default(),
+        author: req.user.map(|user| user.login).unwrap_or_default(),
+        labels: req.labels.into_iter().map(|label| label.name).collect(),
+        reviewers: req
+            .requested_reviewers
+            .into_iter()
+            .map(|user| user.login)
+            .collect(),
+        assignees: req.assignees.into_iter().map(|user| user.login).collect(),
+        milestone: req.milestone.map(|milestone| milestone.title),
+        created_at: req.created_at,
+        updated_at: req.updated_at,
+        draft: req.draft,
+        sha: req.head.map(|head| head.sha).unwrap_or_default(),
+        merge_commit_sha: req.merge_commit_sha.filter(|_| req.merged_at.is_some()),
+        web_url: req.html_url,
+        pipeline_status: PipelineStatus::None,
+        review_status: ReviewStatus::Unknown,
+        approved_by: vec![],
+        approved_by_me: false,
     }
 }
 
+/// The number of results to ask for per page when reading every page of a list
+const GITHUB_PAGE_SIZE: usize = 100;
+
+/// The number of results GitHub's search serves at most, however many pages are read
+const GITHUB_SEARCH_LIMIT: usize = 1000;
+
 /// Query the GitHub API
 fn query_github_api(url: &str, token: &str) -> Result<ureq::Response, Box<ureq::Response>> {
     trace!("Querying {}", url);
@@ -75,14 +451,155 @@ fn query_github_api(url: &str, token: &str) -> Result<ureq::Response, Box<ureq::
     Ok(response)
 }
 
-/// Get the pull requests for the current project
-fn retrieve_github_project_pull_requests(remote: &GitHub) -> Result<Vec<MergeRequest>> {
-    trace!("Querying for GitHub PR for {:?}", remote);
+/// Query the GitHub API, making a conditional request if the URL has been queried before
+fn query_github_api_cached(
+    remote: &GitHub,
+    url: &str,
+) -> Result<serde_json::Value, Box<ureq::Response>> {
+    trace!("Querying {}", url);
+    let mut request = ureq::get(url);
+    request.set("Authorization", &format!("token {}", remote.api_key));
+    remote.cache.query(&mut request, url)
+}
+
+/// Send a request with a JSON body to the GitHub API
+fn send_github_api(
+    method: &str,
+    url: &str,
+    token: &str,
+    body: serde_json::Value,
+) -> Result<serde_json::Value, Box<ureq::Response>> {
+    trace!("Sending {} to {}", method, url);
+    let response = ureq::request(method, url)
+        .set("Authorization", &format!("token {}", token))
+        .send_json(body);
+    if response.error() {
+        return Err(Box::new(response));
+    }
+    Ok(read_json_body(response))
+}
+
+/// Send a query or mutation to the GitHub GraphQL API, which reports errors alongside the data
+fn send_github_graphql(
+    remote: &GitHub,
+    query: &str,
+    variables: serde_json::Value,
+) -> Result<serde_json::Value> {
+    let body = serde_json::json!({ "query": query, "variables": variables });
+    let response = send_github_api(
+        "POST",
+        &get_github_graphql_url(remote),
+        &remote.api_key,
+        body,
+    )
+    .map_err(|response| anyhow!("GraphQL request failed with {}", response.status()))?;
+    if let Some(message) = response
+        .pointer("/errors/0/message")
+        .and_then(|m| m.as_str())
+    {
+        return Err(anyhow!("{}", message));
+    }
+    Ok(response)
+}
+
+/// Get the details of the current project
+fn retrieve_github_project(remote: &GitHub) -> Result<GitHubProject> {
+    let url = &format!("{}/{}", remote.api_root, remote.id);
+    match query_github_api_cached(remote, url) {
+        Ok(buf) => Ok(serde_json::from_value(buf).expect("failed to decode API response")),
+        Err(response) => {
+            debug!("Failed project query response: {:?}", response);
+            match response.status() {
+                401 => Err(anyhow!("API unauthorized")),
+                404 => Err(anyhow!("remote project not found")),
+                _ => Err(anyhow!("failed to read API response")),
+            }
+        }
+    }
+}
+
+/// Open a pull request against the current project
+fn create_github_pull_request(remote: &GitHub, new_req: &NewRequest) -> Result<MergeRequest> {
+    trace!("Creating GitHub PR {:?}", new_req);
     let url = &format!("{}/{}/pulls", remote.api_root, remote.id);
-    let gprs: Vec<GitHubPullRequest> = match query_github_api(url, &remote.api_key) {
-        Ok(response) => {
-            debug!("Successful PR list query response: {:?}", response);
-            let buf = response.into_json().expect("malformed API response");
+    let body = serde_json::json!({
+        "title": new_req.title,
+        "body": new_req.description,
+        "head": new_req.source_branch,
+        "base": new_req.target_branch,
+        "draft": new_req.draft,
+    });
+    match send_github_api("POST", url, &remote.api_key, body) {
+        Ok(buf) => Ok(github_to_mr(
+            serde_json::from_value(buf).expect("failed to decode API response"),
+        )),
+        Err(response) => {
+            debug!("Failed PR creation response: {:?}", response);
+            match response.status() {
+                401 => Err(anyhow!("API unauthorized")),
+                // GitHub rejects duplicate PRs and unknown branches as unprocessable
+                422 => Err(anyhow!(
+                    "the request was rejected; check that the branches exist and that there \
+                     isn't already a request for them"
+                )),
+                _ => Err(anyhow!("failed to read API response")),
+            }
+        }
+    }
+}
+
+/// Get the pull requests for the current project, optionally only those from the given
+/// `owner:branch` or in the given state instead of open
+fn retrieve_github_project_pull_requests(
+    remote: &GitHub,
+    head: Option<&str>,
+    state: Option<&str>,
+) -> Result<Vec<MergeRequest>> {
+    trace!("Querying for GitHub PR for {:?}", remote);
+    let mut params = vec![];
+    if let Some(head) = head {
+        trace!("Filtering PRs by head {}", head);
+        params.push(format!("head={}", encode_query_value(head)));
+    }
+    if let Some(state) = state {
+        params.push(format!("state={}", state));
+    }
+    query_all_github_pull_requests(remote, &params)
+}
+
+/// Get every open pull request whose base is the given branch
+fn retrieve_github_pull_requests_by_base(remote: &GitHub, base: &str) -> Result<Vec<MergeRequest>> {
+    trace!("Querying for GitHub PRs based on {}", base);
+    query_all_github_pull_requests(remote, &[format!("base={}", encode_query_value(base))])
+}
+
+/// Query the GitHub API for the project's pull requests matching the query parameters, reading
+/// through all the pages
+fn query_all_github_pull_requests(remote: &GitHub, params: &[String]) -> Result<Vec<MergeRequest>> {
+    let mut prs = vec![];
+    for page in 1.. {
+        let mut url = format!(
+            "{}/{}/pulls?per_page={}&page={}",
+            remote.api_root, remote.id, GITHUB_PAGE_SIZE, page
+        );
+        for param in params {
+            url.push_str(&format!("&{}", param));
+        }
+        let page_prs = query_github_pull_requests(remote, &url)?;
+        let is_last_page = page_prs.len() < GITHUB_PAGE_SIZE;
+        prs.extend(page_prs);
+        if is_last_page {
+            break;
+        }
+    }
+    Ok(prs)
+}
+
+/// Query the GitHub API for a list of pull requests
+fn query_github_pull_requests(remote: &GitHub, url: &str) -> Result<Vec<MergeRequest>> {
+    let gprs: Vec<GitHubPullRequest> = match query_github_api_cached(remote, url) {
+        Ok(buf) => {
+            debug!("Successful PR list query response: {:?}", buf);
             serde_json::from_value(buf).expect("failed to decode API response")
         }
         Err(response) => {
@@ -97,6 +614,858 @@ fn retrieve_github_project_pull_requests(remote: &GitHub) -> Result<Vec<MergeReq
     Ok(gprs.into_iter().map(github_to_mr).collect())
 }
 
+/// Get the pull requests that contain the commit
+fn retrieve_github_commit_pull_requests(remote: &GitHub, sha: &str) -> Result<Vec<MergeRequest>> {
+    trace!("Querying for GitHub PRs containing {}", sha);
+    let url = &format!("{}/{}/commits/{}/pulls", remote.api_root, remote.id, sha);
+    let gprs: Vec<GitHubPullRequest> = match query_github_api_cached(remote, url) {
+        Ok(buf) => serde_json::from_value(buf).expect("failed to decode API response"),
+        Err(response) => {
+            debug!("Failed commit PRs query response: {:?}", response);
+            return match response.status() {
+                401 => Err(anyhow!("API unauthorized")),
+                404 | 422 => Err(anyhow!("commit not found")),
+                _ => Err(anyhow!("failed to read API response")),
+            };
+        }
+    };
+    Ok(gprs.into_iter().map(github_to_mr).collect())
+}
+
+/// Get a single pull request from the repository
+fn retrieve_github_pull_request(remote: &GitHub, repo: &str, mr_id: i64) -> Result<MergeRequest> {
+    Ok(github_to_mr(query_github_pull_request(
+        remote, repo, mr_id,
+    )?))
+}
+
+/// Get a pull request as GitHub describes it
+fn query_github_pull_request(remote: &GitHub, repo: &str, mr_id: i64) -> Result<GitHubPullRequest> {
+    trace!("Querying for GitHub PR {} for {:?}", mr_id, remote);
+    let url = &format!("{}/{}/pulls/{}", remote.api_root, repo, mr_id);
+    match query_github_api_cached(remote, url) {
+        Ok(buf) => {
+            debug!("Successful PR query response: {:?}", buf);
+            Ok(serde_json::from_value(buf).expect("failed to decode API response"))
+        }
+        Err(response) => {
+            debug!("Failed PR query response: {:?}", response);
+            match response.status() {
+                401 => Err(anyhow!("API unauthorized")),
+                404 => Err(anyhow!("pull request not found")),
+                _ => Err(anyhow!("failed to read API response")),
+            }
+        }
+    }
+}
+
+/// Get a pull request's commits as an mbox of patches
+fn retrieve_github_patches(remote: &GitHub, mr_id: i64) -> Result<String> {
+    let url = &format!("{}/{}/pulls/{}", remote.api_root, remote.id, mr_id);
+    trace!("Querying {} for patches", url);
+    let response = ureq::get(url)
+        .set("Authorization", &format!("token {}", remote.api_key))
+        .set("Accept", "application/vnd.github.patch")
+        .call();
+    if response.error() {
+        debug!("Failed patch query response: {:?}", response);
+        return match response.status() {
+            401 => Err(anyhow!("API unauthorized")),
+            404 => Err(anyhow!("pull request not found")),
+            _ => Err(anyhow!("failed to read API response")),
+        };
+    }
+    Ok(response.into_string()?)
+}
+
+/// Get the combined status of the legacy commit statuses and the check runs for a commit
+fn retrieve_github_commit_status(remote: &GitHub, repo: &str, sha: &str) -> Result<PipelineStatus> {
+    trace!("Querying for GitHub commit status for {}", sha);
+    let url = &format!("{}/{}/commits/{}/status", remote.api_root, repo, sha);
+    let status: GitHubCombinedStatus = match query_github_api_cached(remote, url) {
+        Ok(buf) => serde_json::from_value(buf).expect("failed to decode API response"),
+        Err(response) => {
+            debug!("Failed commit status query response: {:?}", response);
+            return Err(anyhow!("failed to read API response"));
+        }
+    };
+    let url = &format!("{}/{}/commits/{}/check-runs", remote.api_root, repo, sha);
+    let checks: GitHubCheckRuns = match query_github_api_cached(remote, url) {
+        Ok(buf) => serde_json::from_value(buf).expect("failed to decode API response"),
+        Err(response) => {
+            debug!("Failed check runs query response: {:?}", response);
+            return Err(anyhow!("failed to read API response"));
+        }
+    };
+    Ok(combine_github_statuses(&status, &checks))
+}
+
+/// Reduce the commit statuses and check runs down to a single pipeline status
+fn combine_github_statuses(
+    status: &GitHubCombinedStatus,
+    checks: &GitHubCheckRuns,
+) -> PipelineStatus {
+    let mut statuses = vec![];
+    // The combined status is "pending" when there are no statuses at all
+    if status.total_count > 0 {
+        statuses.push(match status.state.as_ref() {
+            "success" => PipelineStatus::Passed,
+            "pending" => PipelineStatus::Running,
+            _ => PipelineStatus::Failed,
+        });
+    }
+    statuses.extend(checks.check_runs.iter().map(|run| {
+        if run.status != "completed" {
+            return PipelineStatus::Running;
+        }
+        match run.conclusion.as_deref() {
+            Some("success") | Some("neutral") | Some("skipped") => PipelineStatus::Passed,
+            _ => PipelineStatus::Failed,
+        }
+    }));
+    [
+        PipelineStatus::Failed,
+        PipelineStatus::Running,
+        PipelineStatus::Passed,
+    ]
+    .into_iter()
+    .find(|candidate| statuses.contains(candidate))
+    .unwrap_or(PipelineStatus::None)
+}
+
+/// Get the login of the user that owns the API key
+fn retrieve_github_username(remote: &GitHub) -> Result<String> {
+    let url = &format!("{}/user", get_github_api_base(remote));
+    match query_github_api_cached(remote, url) {
+        Ok(buf) => {
+            let user: GitHubUser =
+                serde_json::from_value(buf).expect("failed to decode API response");
+            Ok(user.login)
+        }
+        Err(response) => {
+            debug!("Failed user query response: {:?}", response);
+            match response.status() {
+                401 => Err(anyhow!("API unauthorized")),
+                _ => Err(anyhow!("failed to read API response")),
+            }
+        }
+    }
+}
+
+/// Get the user or organization with the given login
+fn retrieve_github_owner(remote: &GitHub, login: &str) -> Result<GitHubOwner> {
+    let url = &format!("{}/users/{}", get_github_api_base(remote), login);
+    match query_github_api_cached(remote, url) {
+        Ok(buf) => Ok(serde_json::from_value(buf).expect("failed to decode API response")),
+        Err(response) => {
+            debug!("Failed owner query response: {:?}", response);
+            match response.status() {
+                401 => Err(anyhow!("API unauthorized")),
+                404 => Err(anyhow!("couldn't find the owner '{}'", login)),
+                _ => Err(anyhow!("failed to read API response")),
+            }
+        }
+    }
+}
+
+/// Get the reviews left on a pull request, oldest first
+fn retrieve_github_reviews(remote: &GitHub, repo: &str, mr_id: i64) -> Result<Vec<GitHubReview>> {
+    trace!("Querying for GitHub reviews for PR {}", mr_id);
+    let url = &format!(
+        "{}/{}/pulls/{}/reviews?per_page=100",
+        remote.api_root, repo, mr_id
+    );
+    match query_github_api_cached(remote, url) {
+        Ok(buf) => Ok(serde_json::from_value(buf).expect("failed to decode API response")),
+        Err(response) => {
+            debug!("Failed reviews query response: {:?}", response);
+            Err(anyhow!("failed to read API response"))
+        }
+    }
+}
+
+/// Submit a review of the pull request
+fn create_github_review(remote: &GitHub, mr_id: i64, event: &str, body: &str) -> Result<()> {
+    trace!("Submitting a {} review of PR {}", event, mr_id);
+    let url = &format!("{}/{}/pulls/{}/reviews", remote.api_root, remote.id, mr_id);
+    let mut review = serde_json::json!({ "event": event });
+    if !body.is_empty() {
+        review["body"] = serde_json::Value::from(body);
+    }
+    match send_github_api("POST", url, &remote.api_key, review) {
+        Ok(_) => Ok(()),
+        Err(response) => {
+            debug!("Failed review response: {:?}", response);
+            match response.status() {
+                401 => Err(anyhow!("API unauthorized")),
+                404 => Err(anyhow!("pull request not found")),
+                422 => Err(anyhow!(
+                    "the review was rejected: {}",
+                    describe_github_rejection(*response)
+                )),
+                _ => Err(anyhow!("failed to read API response")),
+            }
+        }
+    }
+}
+
+/// Describe why GitHub rejected a request, going by the errors in the response
+fn describe_github_rejection(response: ureq::Response) -> String {
+    let body: serde_json::Value = response
+        .into_string()
+        .ok()
+        .and_then(|body| serde_json::from_str(&body).ok())
+        .unwrap_or_default();
+    // Errors are either plain messages or objects describing the invalid field
+    let errors: Vec<&str> = body["errors"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|error| error.as_str().or_else(|| error["message"].as_str()))
+        .collect();
+    if !errors.is_empty() {
+        return errors.join("; ");
+    }
+    body["message"]
+        .as_str()
+        .map_or_else(|| String::from("no reason given"), String::from)
+}
+
+/// Reduce the reviews down to each reviewer's latest approval, rejection, or dismissal, in the
+/// order they were left. Comments don't change a reviewer's standing, so they're skipped.
+fn latest_reviews_by_user(reviews: &[GitHubReview]) -> Vec<(&str, &GitHubReview)> {
+    let mut latest: Vec<(&str, &GitHubReview)> = vec![];
+    for review in reviews {
+        let login = match &review.user {
+            Some(user) => user.login.as_str(),
+            None => continue,
+        };
+        if !["APPROVED", "CHANGES_REQUESTED", "DISMISSED"].contains(&review.state.as_str()) {
+            continue;
+        }
+        latest.retain(|(reviewer, _)| *reviewer != login);
+        latest.push((login, review));
+    }
+    latest
+}
+
+/// Find the user's approval that's still in effect, if any
+fn find_github_approval(reviews: &[GitHubReview], username: &str) -> Option<i64> {
+    latest_reviews_by_user(reviews)
+        .into_iter()
+        .find(|(login, review)| *login == username && review.state == "APPROVED")
+        .map(|(_, review)| review.id)
+}
+
+/// Dismiss a review of the pull request
+fn dismiss_github_review(remote: &GitHub, mr_id: i64, review_id: i64) -> Result<()> {
+    let url = &format!(
+        "{}/{}/pulls/{}/reviews/{}/dismissals",
+        remote.api_root, remote.id, mr_id, review_id
+    );
+    let dismissal = serde_json::json!({
+        "message": "Approval withdrawn",
+        "event": "DISMISS",
+    });
+    match send_github_api("PUT", url, &remote.api_key, dismissal) {
+        Ok(_) => Ok(()),
+        Err(response) => {
+            debug!("Failed dismissal response: {:?}", response);
+            match response.status() {
+                401 | 403 => Err(anyhow!("you aren't allowed to dismiss reviews")),
+                _ => Err(anyhow!("failed to read API response")),
+            }
+        }
+    }
+}
+
+/// Apply the changes to the PR. They're spread across the PR itself, the issue behind it, its
+/// review requests, and (for the draft state) the GraphQL API.
+fn update_github_pull_request(
+    remote: &GitHub,
+    mr_id: i64,
+    update: &ReqUpdate,
+) -> Result<MergeRequest> {
+    // Look the milestone up first so that a typo doesn't leave the PR half-updated
+    let milestone = match &update.milestone {
+        Some(Some(title)) => Some(serde_json::Value::from(retrieve_github_milestone_number(
+            remote, title,
+        )?)),
+        Some(None) => Some(serde_json::Value::Null),
+        None => None,
+    };
+    let mut pr_changes = serde_json::Map::new();
+    if let Some(state) = update.state {
+        let state = match state {
+            StateChange::Close => "closed",
+            StateChange::Reopen => "open",
+        };
+        pr_changes.insert(String::from("state"), serde_json::Value::from(state));
+    }
+    if let Some(target_branch) = &update.target_branch {
+        pr_changes.insert(
+            String::from("base"),
+            serde_json::Value::from(target_branch.as_str()),
+        );
+    }
+    if let Some(title) = &update.title {
+        pr_changes.insert(
+            String::from("title"),
+            serde_json::Value::from(title.as_str()),
+        );
+    }
+    if let Some(description) = &update.description {
+        pr_changes.insert(
+            String::from("body"),
+            serde_json::Value::from(description.as_str()),
+        );
+    }
+    let pr_url = &format!("{}/{}/pulls/{}", remote.api_root, remote.id, mr_id);
+    let mut pr: GitHubPullRequest = if pr_changes.is_empty() {
+        query_github_pull_request(remote, &remote.id, mr_id)?
+    } else {
+        let buf = send_github_change(remote, "PATCH", pr_url, pr_changes.into())?;
+        serde_json::from_value(buf).expect("failed to decode API response")
+    };
+    let mut issue_changes = serde_json::Map::new();
+    if let Some(labels) = &update.labels {
+        issue_changes.insert(
+            String::from("labels"),
+            serde_json::Value::from(labels.clone()),
+        );
+    }
+    if let Some(assignees) = &update.assignees {
+        issue_changes.insert(
+            String::from("assignees"),
+            serde_json::Value::from(assignees.clone()),
+        );
+    }
+    if let Some(milestone) = milestone {
+        issue_changes.insert(String::from("milestone"), milestone);
+    }
+    let mut changed_elsewhere = !issue_changes.is_empty();
+    if changed_elsewhere {
+        let url = &format!("{}/{}/issues/{}", remote.api_root, remote.id, mr_id);
+        send_github_change(remote, "PATCH", url, issue_changes.into())?;
+    }
+    if let Some(reviewers) = &update.reviewers {
+        let requested: Vec<String> = pr
+            .requested_reviewers
+            .iter()
+            .map(|user| user.login.clone())
+            .collect();
+        changed_elsewhere |= update_github_reviewers(remote, mr_id, &requested, reviewers)?;
+    }
+    if let Some(draft) = update.draft.filter(|draft| *draft != pr.draft) {
+        let node_id = pr
+            .node_id
+            .as_deref()
+            .ok_or_else(|| anyhow!("the pull request has no GraphQL ID"))?;
+        set_github_draft(remote, node_id, draft)?;
+        changed_elsewhere = true;
+    }
+    if changed_elsewhere {
+        pr = query_github_pull_request(remote, &remote.id, mr_id)?;
+    }
+    Ok(github_to_mr(pr))
+}
+
+/// Send a change to the PR, or to something belonging to it
+fn send_github_change(
+    remote: &GitHub,
+    method: &str,
+    url: &str,
+    body: serde_json::Value,
+) -> Result<serde_json::Value> {
+    send_github_api(method, url, &remote.api_key, body).map_err(|response| {
+        debug!("Failed PR update response: {:?}", response);
+        match response.status() {
+            401 | 403 => anyhow!("you aren't allowed to change this pull request"),
+            404 => anyhow!("pull request not found"),
+            // E.g. merged PRs can't be reopened, and only collaborators can review
+            422 => anyhow!("the changes were rejected by GitHub"),
+            _ => anyhow!("failed to read API response"),
+        }
+    })
+}
+
+/// Request reviews from the users who are newly listed, and withdraw the requests to those no
+/// longer listed. Returns whether anything changed.
+fn update_github_reviewers(
+    remote: &GitHub,
+    mr_id: i64,
+    requested: &[String],
+    reviewers: &[String],
+) -> Result<bool> {
+    let url = &format!(
+        "{}/{}/pulls/{}/requested_reviewers",
+        remote.api_root, remote.id, mr_id
+    );
+    let added: Vec<&String> = reviewers
+        .iter()
+        .filter(|reviewer| !requested.contains(reviewer))
+        .collect();
+    let removed: Vec<&String> = requested
+        .iter()
+        .filter(|reviewer| !reviewers.contains(reviewer))
+        .collect();
+    if !added.is_empty() {
+        send_github_change(
+            remote,
+            "POST",
+            url,
+            serde_json::json!({ "reviewers": added }),
+        )?;
+    }
+    if !removed.is_empty() {
+        send_github_change(
+            remote,
+            "DELETE",
+            url,
+            serde_json::json!({ "reviewers": removed }),
+        )?;
+    }
+    Ok(!added.is_empty() || !removed.is_empty())
+}
+
+/// Look up the number of the project's milestone with the given title
+fn retrieve_github_milestone_number(remote: &GitHub, title: &str) -> Result<i64> {
+    let url = &format!(
+        "{}/{}/milestones?state=all&per_page=100",
+        remote.api_root, remote.id
+    );
+    let milestones: Vec<GitHubMilestone> = match query_github_api_cached(remote, url) {
+        Ok(buf) => serde_json::from_value(buf).expect("failed to decode API response"),
+        Err(response) => {
+            debug!("Failed milestones query response: {:?}", response);
+            return Err(anyhow!("failed to read API response"));
+        }
+    };
+    milestones
+        .iter()
+        .find(|milestone| milestone.title == title)
+        .map(|milestone| milestone.number)
+        .ok_or_else(|| anyhow!("there's no milestone named {}", title))
+}
+
+/// Convert the PR to a draft, or mark it as ready for review. The REST API can't do either.
+fn set_github_draft(remote: &GitHub, node_id: &str, draft: bool) -> Result<()> {
+    let mutation = if draft {
+        "mutation($id: ID!) { \
+            convertPullRequestToDraft(input: { pullRequestId: $id }) { clientMutationId } }"
+    } else {
+        "mutation($id: ID!) { \
+            markPullRequestReadyForReview(input: { pullRequestId: $id }) { clientMutationId } }"
+    };
+    send_github_graphql(remote, mutation, serde_json::json!({ "id": node_id }))?;
+    Ok(())
+}
+
+/// Split a commit message into the title and body GitHub expects
+fn split_commit_message(message: &str) -> (&str, &str) {
+    let (title, body) = message.split_once('\n').unwrap_or((message, ""));
+    (title.trim(), body.trim())
+}
+
+/// Get the name GitHub's APIs use for the merge method
+fn github_merge_method(method: MergeMethod) -> &'static str {
+    match method {
+        MergeMethod::Merge => "merge",
+        MergeMethod::Squash => "squash",
+        MergeMethod::Rebase => "rebase",
+    }
+}
+
+/// Merge the PR now, provided its head is still the given commit
+fn merge_github_pull_request(
+    remote: &GitHub,
+    mr_id: i64,
+    head_sha: &str,
+    options: &MergeOptions,
+) -> Result<()> {
+    let url = &format!("{}/{}/pulls/{}/merge", remote.api_root, remote.id, mr_id);
+    let mut merge = serde_json::json!({
+        "sha": head_sha,
+        "merge_method": github_merge_method(options.method),
+    });
+    if let Some(message) = &options.message {
+        let (title, body) = split_commit_message(message);
+        merge["commit_title"] = serde_json::Value::from(title);
+        merge["commit_message"] = serde_json::Value::from(body);
+    }
+    match send_github_api("PUT", url, &remote.api_key, merge) {
+        Ok(_) => Ok(()),
+        Err(response) => {
+            debug!("Failed merge response: {:?}", response);
+            match response.status() {
+                401 | 403 => Err(anyhow!("you aren't allowed to merge this pull request")),
+                404 => Err(anyhow!("pull request not found")),
+                405 => Err(anyhow!(
+                    "the pull request can't be merged; it may be a draft, have conflicts, or be \
+                     waiting on required checks or reviews"
+                )),
+                409 => Err(anyhow!(
+                    "the head branch has changed since {}; review the new commits first",
+                    head_sha
+                )),
+                422 => Err(anyhow!("the merge method isn't allowed by the repository")),
+                _ => Err(anyhow!("failed to read API response")),
+            }
+        }
+    }
+}
+
+/// Set the PR to be merged once its requirements are met
+fn enable_github_auto_merge(
+    remote: &GitHub,
+    node_id: &str,
+    head_sha: &str,
+    options: &MergeOptions,
+) -> Result<()> {
+    let (title, body) = options.message.as_deref().map(split_commit_message).unzip();
+    send_github_graphql(
+        remote,
+        "mutation($id: ID!, $method: PullRequestMergeMethod!, $head: GitObjectID, \
+            $title: String, $body: String) { enablePullRequestAutoMerge(input: { \
+            pullRequestId: $id, mergeMethod: $method, expectedHeadOid: $head, \
+            commitHeadline: $title, commitBody: $body }) { clientMutationId } }",
+        serde_json::json!({
+            "id": node_id,
+            "method": github_merge_method(options.method).to_uppercase(),
+            "head": head_sha,
+            "title": title,
+            "body": body,
+        }),
+    )?;
+    Ok(())
+}
+
+/// Delete a branch of the current project
+fn delete_github_branch(remote: &GitHub, branch: &str) -> Result<()> {
+    let url = &format!(
+        "{}/{}/git/refs/heads/{}",
+        remote.api_root, remote.id, branch
+    );
+    trace!("Deleting {}", url);
+    let response = ureq::delete(url)
+        .set("Authorization", &format!("token {}", remote.api_key))
+        .call();
+    if response.error() {
+        debug!("Failed branch deletion response: {:?}", response);
+        return Err(anyhow!("merged, but the branch couldn't be deleted"));
+    }
+    Ok(())
+}
+
+/// Get the comments on a pull request, oldest first. The "issues" endpoint has the comments on the
+/// PR as a whole, and the "pulls" endpoint has the ones on its diff.
+fn retrieve_github_comments(
+    remote: &GitHub,
+    endpoint: &str,
+    mr_id: i64,
+) -> Result<Vec<GitHubComment>> {
+    trace!("Querying for GitHub {} comments for PR {}", endpoint, mr_id);
+    let mut comments = vec![];
+    for page in 1.. {
+        let url = &format!(
+            "{}/{}/{}/{}/comments?per_page={}&page={}",
+            remote.api_root, remote.id, endpoint, mr_id, GITHUB_PAGE_SIZE, page
+        );
+        let page_comments: Vec<GitHubComment> = match query_github_api_cached(remote, url) {
+            Ok(buf) => serde_json::from_value(buf).expect("failed to decode API response"),
+            Err(response) => {
+                debug!("Failed comments query response: {:?}", response);
+                return match response.status() {
+                    401 => Err(anyhow!("API unauthorized")),
+                    404 => Err(anyhow!("pull request not found")),
+                    _ => Err(anyhow!("failed to read API response")),
+                };
+            }
+        };
+        let is_last_page = page_comments.len() < GITHUB_PAGE_SIZE;
+        comments.extend(page_comments);
+        if is_last_page {
+            break;
+        }
+    }
+    Ok(comments)
+}
+
+/// Get whether each review thread is resolved, keyed by the ID of the thread's first comment
+fn retrieve_github_thread_resolutions(remote: &GitHub, mr_id: i64) -> Result<HashMap<i64, bool>> {
+    let (owner, name) = remote
+        .id
+        .split_once('/')
+        .ok_or_else(|| anyhow!("unexpected repository name {}", remote.id))?;
+    let mut resolutions = HashMap::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let response = send_github_graphql(
+            remote,
+            "query($owner: String!, $name: String!, $number: Int!, $after: String) { \
+                repository(owner: $owner, name: $name) { pullRequest(number: $number) { \
+                reviewThreads(first: 100, after: $after) { \
+                pageInfo { hasNextPage endCursor } nodes { isResolved \
+                comments(first: 1) { nodes { databaseId } } } } } } }",
+            serde_json::json!({
+                "owner": owner, "name": name, "number": mr_id, "after": cursor,
+            }),
+        )?;
+        let threads = response
+            .pointer("/data/repository/pullRequest/reviewThreads")
+            .ok_or_else(|| anyhow!("unexpected GraphQL response"))?;
+        let nodes = threads["nodes"]
+            .as_array()
+            .ok_or_else(|| anyhow!("unexpected GraphQL response"))?;
+        resolutions.extend(nodes.iter().filter_map(|thread| {
+            let id = thread.pointer("/comments/nodes/0/databaseId")?.as_i64()?;
+            Some((id, thread["isResolved"].as_bool()?))
+        }));
+        if threads.pointer("/pageInfo/hasNextPage") != Some(&serde_json::Value::Bool(true)) {
+            break;
+        }
+        cursor = threads
+            .pointer("/pageInfo/endCursor")
+            .and_then(|cursor| cursor.as_str())
+            .map(String::from);
+        if cursor.is_none() {
+            break;
+        }
+    }
+    Ok(resolutions)
+}
+
+/// Convert a GitHub comment to a git-req comment
+fn github_to_comment(comment: GitHubComment) -> Comment {
+    Comment {
+        id: comment.id,
+        author: comment
+            .user
+            .map(|user| user.login)
+            .unwrap_or_else(|| String::from("ghost")),
+        body: comment.body,
+        created_at: comment.created_at,
+    }
+}
+
+/// Build the discussion threads from the PR's comments. Each issue comment stands alone, while
+/// review comments are threaded through their replies.
+fn group_github_comments(
+    issue_comments: Vec<GitHubComment>,
+    review_comments: Vec<GitHubComment>,
+    resolved: &HashMap<i64, bool>,
+) -> Vec<Discussion> {
+    let mut discussions: Vec<Discussion> = issue_comments
+        .into_iter()
+        .map(|comment| Discussion {
+            id: comment.id.to_string(),
+            location: None,
+            resolved: None,
+            comments: vec![github_to_comment(comment)],
+        })
+        .collect();
+    let mut threads: Vec<Discussion> = vec![];
+    for comment in review_comments {
+        // Replies always point at the first comment of the thread
+        if let Some(parent) = comment.in_reply_to_id {
+            let parent = parent.to_string();
+            if let Some(thread) = threads.iter_mut().find(|thread| thread.id == parent) {
+                thread.comments.push(github_to_comment(comment));
+                continue;
+            }
+        }
+        threads.push(Discussion {
+            id: comment.id.to_string(),
+            location: Some(CommentLocation {
+                path: comment.path.clone().unwrap_or_default(),
+                line: comment.line,
+                diff_hunk: comment.diff_hunk.clone(),
+            }),
+            resolved: Some(resolved.get(&comment.id).copied().unwrap_or(false)),
+            comments: vec![github_to_comment(comment)],
+        });
+    }
+    discussions.extend(threads);
+    // Timestamps are all in the same ISO 8601 format, so they sort as strings
+    discussions.sort_by(|a, b| a.comments[0].created_at.cmp(&b.comments[0].created_at));
+    discussions
+}
+
+/// Leave a comment on the PR as a whole
+fn create_github_comment(remote: &GitHub, mr_id: i64, body: &str) -> Result<Comment> {
+    trace!("Commenting on PR {}", mr_id);
+    let url = &format!(
+        "{}/{}/issues/{}/comments",
+        remote.api_root, remote.id, mr_id
+    );
+    match send_github_api(
+        "POST",
+        url,
+        &remote.api_key,
+        serde_json::json!({ "body": body }),
+    ) {
+        Ok(buf) => {
+            let comment: GitHubComment =
+                serde_json::from_value(buf).expect("failed to decode API response");
+            Ok(github_to_comment(comment))
+        }
+        Err(response) => {
+            debug!("Failed comment response: {:?}", response);
+            match response.status() {
+                401 | 403 => Err(anyhow!(
+                    "you aren't allowed to comment on this pull request"
+                )),
+                404 => Err(anyhow!("pull request not found")),
+                _ => Err(anyhow!("failed to read API response")),
+            }
+        }
+    }
+}
+
+/// Leave a comment on a line of the new side of the PR's diff
+fn create_github_review_comment(
+    remote: &GitHub,
+    mr_id: i64,
+    head_sha: &str,
+    position: &DiffPosition,
+    body: &str,
+) -> Result<Comment> {
+    trace!(
+        "Commenting on {}:{} of PR {}",
+        position.new_path,
+        position.new_line,
+        mr_id
+    );
+    let url = &format!("{}/{}/pulls/{}/comments", remote.api_root, remote.id, mr_id);
+    let comment = serde_json::json!({
+        "body": body,
+        "commit_id": head_sha,
+        "path": position.new_path,
+        "line": position.new_line,
+        "side": "RIGHT",
+    });
+    match send_github_api("POST", url, &remote.api_key, comment) {
+        Ok(buf) => {
+            let comment: GitHubComment =
+                serde_json::from_value(buf).expect("failed to decode API response");
+            Ok(github_to_comment(comment))
+        }
+        Err(response) => {
+            debug!("Failed review comment response: {:?}", response);
+            match response.status() {
+                401 | 403 => Err(anyhow!(
+                    "you aren't allowed to comment on this pull request"
+                )),
+                404 => Err(anyhow!("pull request not found")),
+                // GitHub rejects lines outside of the diff, and commits that aren't in the PR
+                422 => Err(anyhow!(
+                    "the line couldn't be found in the pull request's diff"
+                )),
+                _ => Err(anyhow!("failed to read API response")),
+            }
+        }
+    }
+}
+
+/// Reduce the reviews down to an overall review status and the users currently approving
+fn summarize_github_reviews(reviews: &[GitHubReview]) -> (ReviewStatus, Vec<String>) {
+    let latest: Vec<(&str, &str)> = latest_reviews_by_user(reviews)
+        .into_iter()
+        .map(|(login, review)| (login, review.state.as_str()))
+        .collect();
+    let approvers: Vec<String> = latest
+        .iter()
+        .filter(|(_, state)| *state == "APPROVED")
+        .map(|(login, _)| String::from(*login))
+        .collect();
+    let status = if latest
+        .iter()
+        .any(|(_, state)| *state == "CHANGES_REQUESTED")
+    {
+        ReviewStatus::ChangesRequested
+    } else if !approvers.is_empty() {
+        ReviewStatus::Approved
+    } else {
+        ReviewStatus::ReviewRequired
+    };
+    (status, approvers)
+}
+
+/// Search the open pull requests within the scope (a `repo:`, `user:` or `org:` qualifier). Search
+/// results are issues, so the requests lack their branch details.
+fn search_github_pull_requests(remote: &GitHub, scope: &str, query: &str) -> Result<FoundReqs> {
+    trace!("Searching GitHub PRs in {} matching {:?}", scope, query);
+    let search = format!("is:pr is:open {} {}", scope, query);
+    let mut found = FoundReqs::default();
+    for page in 1.. {
+        let url = &format!(
+            "{}/search/issues?q={}&per_page={}&page={}",
+            get_github_api_base(remote),
+            encode_query_value(&search),
+            GITHUB_PAGE_SIZE,
+            page
+        );
+        let results: GitHubSearchResults = match query_github_api(url, &remote.api_key) {
+            Ok(response) => {
+                debug!("Successful PR search response: {:?}", response);
+                let buf = response.into_json().expect("malformed API response");
+                serde_json::from_value(buf).expect("failed to decode API response")
+            }
+            Err(response) => {
+                debug!("Failed PR search response: {:?}", response);
+                return match response.status() {
+                    401 => Err(anyhow!("API unauthorized")),
+                    422 => Err(anyhow!("invalid search query")),
+                    _ => Err(anyhow!("failed to read API response")),
+                };
+            }
+        };
+        debug!("Found {} matching PRs", results.total_count);
+        let is_last_page = results.items.len() < GITHUB_PAGE_SIZE;
+        found.total_count = results.total_count.try_into().unwrap_or_default();
+        found
+            .reqs
+            .extend(results.items.into_iter().map(github_to_mr));
+        // GitHub only serves the first thousand search results
+        if is_last_page
+            || found.reqs.len() >= found.total_count
+            || found.reqs.len() >= GITHUB_SEARCH_LIMIT
+        {
+            break;
+        }
+    }
+    Ok(found)
+}
+
+/// Get the repository to query for the PR, which may not be the remote's own
+fn get_github_req_repo<'a>(remote: &'a GitHub, req: &'a MergeRequest) -> &'a str {
+    if req.project.is_empty() {
+        return &remote.id;
+    }
+    &req.project
+}
+
+/// Extract the `owner/name` of a repository from its API URL
+fn get_github_repo_from_api_url(url: &str) -> Option<String> {
+    let (_, repo) = url.split_once("/repos/")?;
+    Some(String::from(repo.trim_end_matches('/')))
+}
+
+/// Get the root of the GitHub API, without the repository namespace
+fn get_github_api_base(remote: &GitHub) -> &str {
+    remote.api_root.trim_end_matches("/repos")
+}
+
+/// Get the URL of the GraphQL API. GitHub Enterprise serves it beside the REST API's version
+/// path rather than under it.
+fn get_github_graphql_url(remote: &GitHub) -> String {
+    let base = get_github_api_base(remote);
+    match base.strip_suffix("/v3") {
+        Some(api) => format!("{}/graphql", api),
+        None => format!("{}/graphql", base),
+    }
+}
+
 /// Extract the project name from a Github origin URL
 pub fn get_github_project_name(origin: &str) -> Option<String> {
     trace!("Getting project name for: {}", origin);
@@ -109,6 +1478,141 @@ pub fn get_github_project_name(origin: &str) -> Option<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::remotes::testing::{MockResponse, MockServer};
+
+    fn make_check_run(status: &str, conclusion: Option<&str>) -> GitHubCheckRun {
+        GitHubCheckRun {
+            status: String::from(status),
+            conclusion: conclusion.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_combine_github_statuses_none() {
+        let status = GitHubCombinedStatus {
+            state: String::from("pending"),
+            total_count: 0,
+        };
+        let checks = GitHubCheckRuns {
+            total_count: 0,
+            check_runs: vec![],
+        };
+        assert_eq!(
+            PipelineStatus::None,
+            combine_github_statuses(&status, &checks)
+        );
+    }
+
+    #[test]
+    fn test_combine_github_statuses_running() {
+        let status = GitHubCombinedStatus {
+            state: String::from("success"),
+            total_count: 1,
+        };
+        let checks = GitHubCheckRuns {
+            total_count: 2,
+            check_runs: vec![
+                make_check_run("completed", Some("success")),
+                make_check_run("in_progress", None),
+            ],
+        };
+        assert_eq!(
+            PipelineStatus::Running,
+            combine_github_statuses(&status, &checks)
+        );
+    }
+
+    #[test]
+    fn test_combine_github_statuses_failed() {
+        let status = GitHubCombinedStatus {
+            state: String::from("pending"),
+            total_count: 1,
+        };
+        let checks = GitHubCheckRuns {
+            total_count: 1,
+            check_runs: vec![make_check_run("completed", Some("timed_out"))],
+        };
+        assert_eq!(
+            PipelineStatus::Failed,
+            combine_github_statuses(&status, &checks)
+        );
+    }
+
+    fn make_review(login: &str, state: &str) -> GitHubReview {
+        GitHubReview {
+            id: 1,
+            user: Some(GitHubUser {
+                login: String::from(login),
+            }),
+            state: String::from(state),
+        }
+    }
+
+    #[test]
+    fn test_find_github_approval() {
+        let mut reviews = vec![
+            make_review("aru", "APPROVED"),
+            make_review("other", "APPROVED"),
+            make_review("aru", "COMMENTED"),
+        ];
+        for (index, review) in reviews.iter_mut().enumerate() {
+            review.id = index as i64;
+        }
+        assert_eq!(Some(0), find_github_approval(&reviews, "aru"));
+        assert_eq!(None, find_github_approval(&reviews, "nobody"));
+    }
+
+    #[test]
+    fn test_find_github_approval_superseded() {
+        let reviews = vec![
+            make_review("aru", "APPROVED"),
+            make_review("aru", "CHANGES_REQUESTED"),
+        ];
+        assert_eq!(None, find_github_approval(&reviews, "aru"));
+    }
+
+    #[test]
+    fn test_summarize_github_reviews_none() {
+        let (status, approvers) = summarize_github_reviews(&[]);
+        assert_eq!(ReviewStatus::ReviewRequired, status);
+        assert!(approvers.is_empty());
+    }
+
+    #[test]
+    fn test_summarize_github_reviews_approved() {
+        let reviews = vec![
+            make_review("alice", "CHANGES_REQUESTED"),
+            make_review("bob", "APPROVED"),
+            make_review("alice", "APPROVED"),
+            make_review("alice", "COMMENTED"),
+        ];
+        let (status, approvers) = summarize_github_reviews(&reviews);
+        assert_eq!(ReviewStatus::Approved, status);
+        assert_eq!(vec!["bob", "alice"], approvers);
+    }
+
+    #[test]
+    fn test_summarize_github_reviews_changes_requested() {
+        let reviews = vec![
+            make_review("alice", "APPROVED"),
+            make_review("bob", "CHANGES_REQUESTED"),
+        ];
+        let (status, approvers) = summarize_github_reviews(&reviews);
+        assert_eq!(ReviewStatus::ChangesRequested, status);
+        assert_eq!(vec!["alice"], approvers);
+    }
+
+    #[test]
+    fn test_get_github_repo_from_api_url() {
+        assert_eq!(
+            Some(String::from("arusahni/git-req")),
+            get_github_repo_from_api_url("https://api.github.com/repos/arusahni/git-req")
+        );
+        assert_eq!(
+            None,
+            get_github_repo_from_api_url("https://api.github.com/user")
+        );
+    }
 
     #[test]
     fn test_get_github_project_name_ssh() {
@@ -137,4 +1641,443 @@ mod tests {
         assert!(name.is_some());
         assert_eq!("my_org/my_project", name.unwrap());
     }
+
+    fn make_remote(server: &MockServer) -> GitHub {
+        GitHub {
+            id: String::from("arusahni/git-req"),
+            domain: String::from("github.com"),
+            name: String::from("git-req"),
+            origin: String::from("git@github.com:arusahni/git-req.git"),
+            api_root: format!("{}/repos", server.url),
+            api_key: String::from("token"),
+            cache: ResponseCache::default(),
+        }
+    }
+
+    /// A pull request of the remote's repository as the API returns it, with the given fields
+    /// overridden
+    fn github_pr_json(overrides: serde_json::Value) -> serde_json::Value {
+        let mut pr = serde_json::json!({
+            "id": 1, "node_id": "PR_1", "number": 17, "title": "Add edit", "body": null,
+            "state": "open", "merged_at": null, "draft": false,
+            "html_url": "https://github.com/arusahni/git-req/pull/17",
+            "user": {"login": "aru"}, "labels": [], "requested_reviewers": [],
+            "created_at": "2020-01-01T00:00:00Z", "updated_at": "2020-01-02T00:00:00Z",
+            "head": {"ref": "add-edit", "sha": "abc"},
+            "base": {"ref": "main", "sha": "def", "repo": {"full_name": "arusahni/git-req"}}
+        });
+        if let serde_json::Value::Object(overrides) = overrides {
+            for (field, value) in overrides {
+                pr[field] = value;
+            }
+        }
+        pr
+    }
+
+    #[test]
+    fn test_create_github_pull_request() {
+        let server = MockServer::start();
+        server.respond(
+            "POST",
+            "/repos/arusahni/git-req/pulls",
+            MockResponse::json(
+                &github_pr_json(serde_json::json!({
+                    "title": "Add create", "body": "Details", "draft": true,
+                    "head": {"ref": "add-create", "sha": "abc"},
+                }))
+                .to_string(),
+            ),
+        );
+        let mut remote = make_remote(&server);
+        let new_req = NewRequest {
+            title: String::from("Add create"),
+            description: String::from("Details"),
+            source_branch: String::from("add-create"),
+            target_branch: String::from("main"),
+            draft: true,
+        };
+        let mr = remote.create_req(&new_req).unwrap();
+        assert_eq!(17, mr.id);
+        assert_eq!("add-create", mr.source_branch);
+        assert!(mr.draft);
+        let requests = server.requests_to("/repos/arusahni/git-req/pulls");
+        assert_eq!(Some("token token"), requests[0].header("Authorization"));
+    }
+
+    fn make_comment(id: i64, created_at: &str, in_reply_to_id: Option<i64>) -> GitHubComment {
+        GitHubComment {
+            id,
+            user: Some(GitHubUser {
+                login: String::from("aru"),
+            }),
+            body: format!("Comment {}", id),
+            created_at: String::from(created_at),
+            in_reply_to_id,
+            path: Some(String::from("src/main.rs")),
+            line: Some(10),
+            diff_hunk: None,
+        }
+    }
+
+    #[test]
+    fn test_group_github_comments() {
+        let issue_comments = vec![GitHubComment {
+            path: None,
+            line: None,
+            ..make_comment(1, "2020-01-02T00:00:00Z", None)
+        }];
+        let review_comments = vec![
+            make_comment(2, "2020-01-01T00:00:00Z", None),
+            make_comment(3, "2020-01-03T00:00:00Z", None),
+            make_comment(4, "2020-01-04T00:00:00Z", Some(2)),
+        ];
+        let resolved = HashMap::from([(2, true)]);
+        let discussions = group_github_comments(issue_comments, review_comments, &resolved);
+        assert_eq!(
+            vec!["2", "1", "3"],
+            discussions
+                .iter()
+                .map(|discussion| discussion.id.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some(true), discussions[0].resolved);
+        assert_eq!(2, discussions[0].comments.len());
+        assert_eq!(None, discussions[1].location);
+        assert_eq!(None, discussions[1].resolved);
+        assert_eq!(Some(false), discussions[2].resolved);
+    }
+
+    #[test]
+    fn test_retrieve_github_thread_resolutions_pages() {
+        let server = MockServer::start();
+        let page = |id: i64, resolved: bool, next: Option<&str>| {
+            let threads = serde_json::json!({
+                "pageInfo": {"hasNextPage": next.is_some(), "endCursor": next},
+                "nodes": [{"isResolved": resolved, "comments": {"nodes": [{"databaseId": id}]}}],
+            });
+            let body = serde_json::json!({
+                "data": {"repository": {"pullRequest": {"reviewThreads": threads}}},
+            });
+            MockResponse::json(&body.to_string())
+        };
+        server.respond("POST", "/graphql", page(1, true, Some("cursor-1")));
+        server.respond("POST", "/graphql", page(2, false, None));
+        let remote = make_remote(&server);
+        let resolutions = retrieve_github_thread_resolutions(&remote, 17).unwrap();
+        assert_eq!(HashMap::from([(1, true), (2, false)]), resolutions);
+        assert_eq!(2, server.requests_to("/graphql").len());
+    }
+
+    #[test]
+    fn test_get_github_graphql_url() {
+        let mut remote = GitHub {
+            id: String::from("arusahni/git-req"),
+            domain: String::from("github.com"),
+            name: String::from("git-req"),
+            origin: String::from("git@github.com:arusahni/git-req.git"),
+            api_root: String::from("https://api.github.com/repos"),
+            api_key: String::from("token"),
+            cache: ResponseCache::default(),
+        };
+        assert_eq!(
+            "https://api.github.com/graphql",
+            get_github_graphql_url(&remote)
+        );
+        remote.api_root = String::from("https://github.example.com/api/v3/repos");
+        assert_eq!(
+            "https://github.example.com/api/graphql",
+            get_github_graphql_url(&remote)
+        );
+    }
+
+    #[test]
+    fn test_split_commit_message() {
+        assert_eq!(
+            ("Add merge", "Merges requests.\n\nCloses #1."),
+            split_commit_message("Add merge\n\nMerges requests.\n\nCloses #1.\n")
+        );
+        assert_eq!(("Add merge", ""), split_commit_message("Add merge"));
+    }
+
+    #[test]
+    fn test_update_req() {
+        let server = MockServer::start();
+        server.respond(
+            "PATCH",
+            "/repos/arusahni/git-req/pulls/17",
+            MockResponse::json(
+                &github_pr_json(serde_json::json!({
+                    "title": "Add create", "state": "closed",
+                    "head": {"ref": "add-create", "sha": "abc"},
+                    "base": {"ref": "release-1.3", "sha": "def"},
+                }))
+                .to_string(),
+            ),
+        );
+        let mut remote = make_remote(&server);
+        let update = ReqUpdate {
+            state: Some(StateChange::Close),
+            target_branch: Some(String::from("release-1.3")),
+            ..ReqUpdate::default()
+        };
+        let mr = remote.update_req(17, &update).unwrap();
+        assert_eq!("closed", mr.state);
+        assert_eq!("release-1.3", mr.target_branch);
+        let requests = server.requests_to("/repos/arusahni/git-req/pulls/17");
+        assert_eq!("PATCH", requests[0].method);
+    }
+
+    #[test]
+    fn test_update_req_metadata() {
+        let server = MockServer::start();
+        let pr_path = "/repos/arusahni/git-req/pulls/17";
+        let reviewers_path = "/repos/arusahni/git-req/pulls/17/requested_reviewers";
+        for (draft, reviewer) in [(true, "old"), (false, "new")] {
+            let pr = github_pr_json(serde_json::json!({
+                "draft": draft, "requested_reviewers": [{"login": reviewer}],
+            }));
+            server.respond("GET", pr_path, MockResponse::json(&pr.to_string()));
+        }
+        server.respond(
+            "PATCH",
+            "/repos/arusahni/git-req/issues/17",
+            MockResponse::json("{}"),
+        );
+        server.respond("POST", reviewers_path, MockResponse::json("{}"));
+        server.respond("DELETE", reviewers_path, MockResponse::json("{}"));
+        server.respond(
+            "POST",
+            "/graphql",
+            MockResponse::json(r#"{"data": {"markPullRequestReadyForReview": {}}}"#),
+        );
+        let mut remote = make_remote(&server);
+        let update = ReqUpdate {
+            labels: Some(vec![String::from("ui")]),
+            reviewers: Some(vec![String::from("new")]),
+            draft: Some(false),
+            ..ReqUpdate::default()
+        };
+        let mr = remote.update_req(17, &update).unwrap();
+        assert!(!mr.draft);
+        assert_eq!(vec!["new"], mr.reviewers);
+        let methods: Vec<String> = server
+            .requests_to(reviewers_path)
+            .into_iter()
+            .map(|request| request.method)
+            .collect();
+        assert_eq!(vec!["POST", "DELETE"], methods);
+        assert_eq!(1, server.requests_to("/graphql").len());
+    }
+
+    #[test]
+    fn test_update_req_unknown_milestone() {
+        let server = MockServer::start();
+        server.respond(
+            "GET",
+            "/repos/arusahni/git-req/milestones",
+            MockResponse::json(r#"[{"number": 2, "title": "1.2"}]"#),
+        );
+        let mut remote = make_remote(&server);
+        let update = ReqUpdate {
+            title: Some(String::from("Add edit")),
+            milestone: Some(Some(String::from("1.3"))),
+            ..ReqUpdate::default()
+        };
+        assert!(remote.update_req(17, &update).is_err());
+        assert!(server
+            .requests_to("/repos/arusahni/git-req/pulls/17")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_get_req_patches() {
+        let server = MockServer::start();
+        let patches = include_str!("../../tests/fixtures/request.patch");
+        let path = "/repos/arusahni/git-req/pulls/17";
+        server.respond("GET", path, MockResponse::text(patches));
+        let mut remote = make_remote(&server);
+        assert_eq!(patches, remote.get_req_patches(17).unwrap());
+        let requests = server.requests_to(path);
+        assert_eq!(
+            Some("application/vnd.github.patch"),
+            requests[0].header("Accept")
+        );
+        assert!(remote.get_req_patches(18).is_err());
+    }
+
+    #[test]
+    fn test_find_merged_reqs_by_source_branch() {
+        let server = MockServer::start();
+        let pr_json = |number: i64, merged_at: Option<&str>| {
+            github_pr_json(serde_json::json!({
+                "id": number, "number": number, "title": "Add stack", "state": "closed",
+                "merged_at": merged_at, "merge_commit_sha": format!("m{}", number),
+                "html_url": format!("https://github.com/arusahni/git-req/pull/{}", number),
+                "head": {"ref": "add-stack", "sha": "abc"},
+            }))
+        };
+        let path = "/repos/arusahni/git-req/pulls";
+        server.respond(
+            "GET",
+            path,
+            MockResponse::json(&format!(
+                "[{}, {}]",
+                pr_json(16, None),
+                pr_json(17, Some("2020-01-02T00:00:00Z"))
+            )),
+        );
+        let mut remote = make_remote(&server);
+        let reqs = remote
+            .find_merged_reqs_by_source_branch("add-stack")
+            .unwrap();
+        assert_eq!(vec![17], reqs.iter().map(|req| req.id).collect::<Vec<_>>());
+        assert_eq!(Some("m17"), reqs[0].merge_commit_sha.as_deref());
+        let query = &server.requests_to(path)[0].query;
+        assert!(query.contains("state=closed"));
+        assert!(query.contains("add-stack"));
+    }
+
+    #[test]
+    fn test_find_reqs_by_target_branch() {
+        let server = MockServer::start();
+        let page = |numbers: std::ops::RangeInclusive<usize>| {
+            let prs: Vec<serde_json::Value> = numbers
+                .map(|number| {
+                    github_pr_json(serde_json::json!({
+                        "id": number, "number": number, "title": format!("Request {}", number),
+                        "html_url": format!("https://github.com/arusahni/git-req/pull/{}", number),
+                        "head": {"ref": format!("part-{}", number), "sha": "abc"},
+                        "base": {"ref": "release-1.2", "sha": "def",
+                                 "repo": {"full_name": "arusahni/git-req"}},
+                    }))
+                })
+                .collect();
+            MockResponse::json(&serde_json::Value::Array(prs).to_string())
+        };
+        let path = "/repos/arusahni/git-req/pulls";
+        server.respond("GET", path, page(1..=GITHUB_PAGE_SIZE));
+        server.respond(
+            "GET",
+            path,
+            page(GITHUB_PAGE_SIZE + 1..=GITHUB_PAGE_SIZE + 2),
+        );
+        let mut remote = make_remote(&server);
+        let reqs = remote.find_reqs_by_target_branch("release-1.2").unwrap();
+        assert_eq!(GITHUB_PAGE_SIZE + 2, reqs.len());
+        let queries: Vec<String> = server
+            .requests_to(path)
+            .into_iter()
+            .map(|request| request.query)
+            .collect();
+        assert_eq!(2, queries.len());
+        assert!(queries[0].contains("base=release-1.2"));
+        assert!(queries[1].contains("page=2"));
+    }
+
+    #[test]
+    fn test_create_github_review_rejected() {
+        let server = MockServer::start();
+        let path = "/repos/arusahni/git-req/pulls/17/reviews";
+        let rejection = |body: &str| MockResponse {
+            status: 422,
+            ..MockResponse::json(body)
+        };
+        server.respond(
+            "POST",
+            path,
+            rejection(
+                r#"{"message": "Unprocessable Entity",
+                    "errors": ["Can not approve your own pull request"]}"#,
+            ),
+        );
+        server.respond(
+            "POST",
+            path,
+            rejection(r#"{"message": "Validation Failed"}"#),
+        );
+        server.respond("POST", path, MockResponse::status(422));
+        let remote = make_remote(&server);
+        let error = create_github_review(&remote, 17, "APPROVE", "").unwrap_err();
+        assert_eq!(
+            "the review was rejected: Can not approve your own pull request",
+            error.to_string()
+        );
+        let error = create_github_review(&remote, 17, "REQUEST_CHANGES", "").unwrap_err();
+        assert_eq!(
+            "the review was rejected: Validation Failed",
+            error.to_string()
+        );
+        let error = create_github_review(&remote, 17, "COMMENT", "").unwrap_err();
+        assert_eq!(
+            "the review was rejected: no reason given",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn test_search_reqs_details() {
+        let server = MockServer::start();
+        let issue_json = |number: i64| {
+            format!(
+                r#"{{"id": {0}, "number": {0}, "title": "Request {0}", "body": null,
+                    "state": "open", "html_url": "https://github.com/arusahni/git-req/pull/{0}",
+                    "user": {{"login": "aru"}},
+                    "created_at": "2020-01-01T00:00:00Z", "updated_at": "2020-01-01T00:00:00Z",
+                    "repository_url": "https://api.github.com/repos/arusahni/git-req"}}"#,
+                number
+            )
+        };
+        server.respond(
+            "GET",
+            "/search/issues",
+            MockResponse::json(&format!(
+                r#"{{"total_count": 2, "items": [{}, {}]}}"#,
+                issue_json(16),
+                issue_json(17)
+            )),
+        );
+        server.respond(
+            "GET",
+            "/repos/arusahni/git-req/pulls/17",
+            MockResponse::json(&github_pr_json(serde_json::json!({})).to_string()),
+        );
+        let mut remote = make_remote(&server);
+        let mut reqs = remote.search_reqs("edit").unwrap().reqs;
+        assert_eq!(2, reqs.len());
+        assert_eq!("arusahni/git-req", reqs[0].project);
+        // The search results are listed as they are, without fetching each PR
+        assert!(server
+            .requests_to("/repos/arusahni/git-req/pulls/16")
+            .is_empty());
+        assert!(server
+            .requests_to("/repos/arusahni/git-req/pulls/17")
+            .is_empty());
+        remote.fill_req_details(&mut reqs, &|req| req.id == 17);
+        assert!(server
+            .requests_to("/repos/arusahni/git-req/pulls/16")
+            .is_empty());
+        assert_eq!("", reqs[0].sha);
+        assert_eq!("abc", reqs[1].sha);
+        assert_eq!("main", reqs[1].target_branch);
+    }
+
+    #[test]
+    fn test_get_group_req_names() {
+        let server = MockServer::start();
+        server.respond(
+            "GET",
+            "/users/arusahni",
+            MockResponse::json(r#"{"login": "arusahni", "type": "Organization"}"#),
+        );
+        server.respond(
+            "GET",
+            "/search/issues",
+            MockResponse::json(r#"{"total_count": 1500, "items": []}"#),
+        );
+        let mut remote = make_remote(&server);
+        let found = remote.get_group_req_names().unwrap();
+        assert_eq!(1500, found.total_count);
+        let query = &server.requests_to("/search/issues")[0].query;
+        assert!(query.contains("org%3Aarusahni"), "{}", query);
+    }
 }
@@ -1,5 +1,10 @@
 use crate::git;
-use crate::remotes::{MergeRequest, Remote};
+use crate::remotes::{
+    encode_query_value, for_each_req_concurrently, read_json_body, Comment, CommentLocation,
+    DiffPosition, Discussion, FoundReqs, MergeMethod, MergeOptions, MergeOutcome, MergeRequest,
+    NewRequest, PipelineStatus, Remote, ReqRevision, ReqUpdate, ResponseCache, ReviewStatus,
+    StateChange,
+};
 use anyhow::{anyhow, Result};
 use git_url_parse::GitUrl;
 use log::{debug, error, trace};
@@ -13,9 +18,11 @@ pub struct GitLab {
     pub name: String,
     pub namespace: String,
     pub full_path: String,
+    #[allow(dead_code)]
     pub origin: String,
     pub api_root: String,
     pub api_key: String,
+    pub cache: ResponseCache,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -24,10 +31,117 @@ struct GitLabMergeRequest {
     iid: i64,
     title: String,
     description: Option<String>,
+    state: String,
     target_branch: String,
     source_branch: String,
+    author: GitLabUser,
+    #[serde(default)]
+    labels: Vec<String>,
+    // Absent on GitLab instances older than 13.7
+    #[serde(default)]
+    reviewers: Vec<GitLabUser>,
+    #[serde(default)]
+    assignees: Vec<GitLabUser>,
+    milestone: Option<GitLabMilestone>,
+    created_at: String,
+    updated_at: String,
     sha: String,
+    // Absent until merged, and for fast-forward merges
+    merge_commit_sha: Option<String>,
+    squash_commit_sha: Option<String>,
     web_url: String,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    work_in_progress: bool,
+    #[serde(default)]
+    merge_when_pipeline_succeeds: bool,
+    // Only present when querying for a single MR
+    head_pipeline: Option<GitLabPipeline>,
+    // Absent on GitLab instances older than 12.6
+    references: Option<GitLabReferences>,
+    // Only present when querying for a single MR
+    diff_refs: Option<GitLabDiffRefs>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitLabDiffRefs {
+    base_sha: String,
+    head_sha: String,
+    start_sha: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitLabMilestone {
+    id: i64,
+    title: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitLabReferences {
+    full: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitLabPipeline {
+    id: i64,
+    status: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitLabUser {
+    id: i64,
+    username: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitLabApprover {
+    user: GitLabUser,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitLabApprovals {
+    approved: bool,
+    #[serde(default)]
+    approved_by: Vec<GitLabApprover>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitLabNote {
+    id: i64,
+    body: String,
+    author: GitLabUser,
+    created_at: String,
+    #[serde(default)]
+    system: bool,
+    #[serde(default)]
+    resolvable: bool,
+    #[serde(default)]
+    resolved: bool,
+    // Only present for notes on the diff
+    position: Option<GitLabPosition>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitLabPosition {
+    new_path: Option<String>,
+    old_path: Option<String>,
+    new_line: Option<i64>,
+    old_line: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitLabDiscussion {
+    id: String,
+    notes: Vec<GitLabNote>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GitLabVersion {
+    id: i64,
+    head_commit_sha: String,
+    base_commit_sha: String,
+    created_at: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -37,6 +151,8 @@ struct GitLabProject {
     name: String,
     path: String,
     path_with_namespace: String,
+    // Absent for projects with an empty repository
+    default_branch: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -64,18 +180,188 @@ impl Remote for GitLab {
         self.get_remote_req_branch(mr_id)
     }
 
+    fn get_local_req_branch_from(&mut self, mr: &MergeRequest) -> String {
+        mr.source_branch.clone()
+    }
+
     fn get_remote_req_branch(&mut self, mr_id: i64) -> Result<String> {
         query_gitlab_branch_name(self, mr_id)
     }
 
+    fn get_remote_req_head_ref(&mut self, mr_id: i64) -> String {
+        format!("refs/merge-requests/{}/head", mr_id)
+    }
+
     fn get_req_names(&mut self) -> Result<Vec<MergeRequest>> {
-        retrieve_gitlab_project_merge_requests(self)
+        retrieve_gitlab_project_merge_requests(self, "opened", None, None)
+    }
+
+    fn find_reqs_by_source_branch(&mut self, branch: &str) -> Result<Vec<MergeRequest>> {
+        retrieve_gitlab_project_merge_requests(self, "opened", None, Some(branch))
+    }
+
+    fn find_reqs_by_target_branch(&mut self, branch: &str) -> Result<Vec<MergeRequest>> {
+        let url = format!(
+            "{}/projects/{}/merge_requests?state=opened&target_branch={}",
+            self.api_root,
+            self.id,
+            encode_query_value(branch)
+        );
+        query_all_gitlab_merge_requests(self, &url, "remote project not found")
+    }
+
+    fn find_merged_reqs_by_source_branch(&mut self, branch: &str) -> Result<Vec<MergeRequest>> {
+        retrieve_gitlab_project_merge_requests(self, "merged", None, Some(branch))
+    }
+
+    fn find_reqs_by_commit(&mut self, sha: &str) -> Result<Vec<MergeRequest>> {
+        let url = format!(
+            "{}/projects/{}/repository/commits/{}/merge_requests",
+            self.api_root, self.id, sha
+        );
+        query_gitlab_merge_requests(self, &url, "commit not found")
+    }
+
+    fn get_group_req_names(&mut self) -> Result<FoundReqs> {
+        retrieve_gitlab_group_merge_requests(self).map(FoundReqs::complete)
+    }
+
+    fn get_req(&mut self, mr_id: i64) -> Result<MergeRequest> {
+        Ok(gitlab_to_mr(query_gitlab_merge_request(
+            self, &self.id, mr_id,
+        )?))
+    }
+
+    fn populate_pipeline_statuses(&mut self, reqs: &mut [MergeRequest]) {
+        let remote = &*self;
+        for_each_req_concurrently(reqs, |req| {
+            let project = get_gitlab_req_project(remote, req);
+            match query_gitlab_merge_request(remote, &project, req.id) {
+                Ok(full_req) => req.pipeline_status = gitlab_pipeline_status(&full_req),
+                Err(err) => debug!("Couldn't get the pipeline for MR {}: {}", req.id, err),
+            }
+        });
+    }
+
+    fn populate_approvals(&mut self, reqs: &mut [MergeRequest]) {
+        let username = query_gitlab_current_user(self)
+            .map(|user| user.username)
+            .map_err(|err| debug!("Couldn't get the current user: {}", err))
+            .ok();
+        let remote = &*self;
+        for_each_req_concurrently(reqs, |req| {
+            let project = get_gitlab_req_project(remote, req);
+            match query_gitlab_approvals(remote, &project, req.id) {
+                Ok(approvals) => {
+                    req.review_status = if approvals.approved && !approvals.approved_by.is_empty() {
+                        ReviewStatus::Approved
+                    } else {
+                        ReviewStatus::ReviewRequired
+                    };
+                    req.approved_by = approvals
+                        .approved_by
+                        .into_iter()
+                        .map(|approver| approver.user.username)
+                        .collect();
+                    req.approved_by_me = username
+                        .as_ref()
+                        .is_some_and(|username| req.approved_by.contains(username));
+                }
+                Err(err) => debug!("Couldn't get the approvals for MR {}: {}", req.id, err),
+            }
+        });
+    }
+
+    fn get_default_branch(&mut self) -> Result<String> {
+        query_gitlab_project(self)?
+            .default_branch
+            .ok_or_else(|| anyhow!("the project has no default branch"))
+    }
+
+    fn create_req(&mut self, new_req: &NewRequest) -> Result<MergeRequest> {
+        create_gitlab_merge_request(self, new_req)
+    }
+
+    fn approve_req(&mut self, mr_id: i64) -> Result<()> {
+        post_gitlab_approval(self, mr_id, "approve")
+    }
+
+    fn unapprove_req(&mut self, mr_id: i64) -> Result<()> {
+        post_gitlab_approval(self, mr_id, "unapprove")
+    }
+
+    fn update_req(&mut self, mr_id: i64, update: &ReqUpdate) -> Result<MergeRequest> {
+        update_gitlab_merge_request(self, mr_id, update)
+    }
+
+    fn merge_req(
+        &mut self,
+        mr_id: i64,
+        head_sha: &str,
+        options: &MergeOptions,
+    ) -> Result<MergeOutcome> {
+        merge_gitlab_merge_request(self, mr_id, head_sha, options)
+    }
+
+    fn get_req_patches(&mut self, mr_id: i64) -> Result<String> {
+        retrieve_gitlab_patches(self, mr_id)
+    }
+
+    fn get_discussions(&mut self, mr_id: i64) -> Result<Vec<Discussion>> {
+        let discussions = query_gitlab_discussions(self, mr_id)?;
+        Ok(discussions
+            .into_iter()
+            .filter_map(gitlab_to_discussion)
+            .collect())
+    }
+
+    fn create_comment(&mut self, mr_id: i64, body: &str) -> Result<Comment> {
+        post_gitlab_note(self, mr_id, body)
+    }
+
+    fn create_inline_comment(
+        &mut self,
+        mr_id: i64,
+        head_sha: &str,
+        position: &DiffPosition,
+        body: &str,
+    ) -> Result<Comment> {
+        let diff_refs = query_gitlab_merge_request(self, &self.id, mr_id)?
+            .diff_refs
+            .ok_or_else(|| anyhow!("the merge request has no diff"))?;
+        if diff_refs.head_sha != head_sha {
+            return Err(anyhow!(
+                "the merge request changed while commenting; try again"
+            ));
+        }
+        post_gitlab_discussion(self, mr_id, body, gitlab_position(&diff_refs, position))
+    }
+
+    fn get_req_revisions(&mut self, mr_id: i64) -> Result<Vec<ReqRevision>> {
+        let versions = query_gitlab_versions(self, mr_id)?;
+        Ok(versions
+            .into_iter()
+            .map(|version| ReqRevision {
+                base_sha: version.base_commit_sha,
+                head_sha: version.head_commit_sha,
+                created_at: version.created_at,
+            })
+            .collect())
+    }
+
+    fn search_reqs(&mut self, query: &str) -> Result<FoundReqs> {
+        retrieve_gitlab_project_merge_requests(self, "opened", Some(query), None)
+            .map(FoundReqs::complete)
     }
 
     fn has_useful_branch_names(&mut self) -> bool {
         true
     }
 
+    fn get_response_cache(&self) -> &ResponseCache {
+        &self.cache
+    }
+
     fn has_virtual_remote_branch_names(&mut self) -> bool {
         false
     }
@@ -90,6 +376,80 @@ fn query_gitlab_api(url: &str, token: &str) -> Result<ureq::Response, Box<ureq::
     Ok(response)
 }
 
+/// Query the GitLab API, making a conditional request if the URL has been queried before
+fn query_gitlab_api_cached(
+    remote: &GitLab,
+    url: &str,
+) -> Result<serde_json::Value, Box<ureq::Response>> {
+    let mut request = ureq::get(url);
+    request.set("PRIVATE-TOKEN", &remote.api_key);
+    remote.cache.query(&mut request, url)
+}
+
+/// Send a request with a JSON body to the GitLab API
+fn send_gitlab_api(
+    method: &str,
+    url: &str,
+    token: &str,
+    body: serde_json::Value,
+) -> Result<serde_json::Value, Box<ureq::Response>> {
+    trace!("Sending {} to {}", method, url);
+    let response = ureq::request(method, url)
+        .set("PRIVATE-TOKEN", token)
+        .send_json(body);
+    if response.error() {
+        return Err(Box::new(response));
+    }
+    Ok(read_json_body(response))
+}
+
+/// Query the GitLab API for the details of the remote's project
+fn query_gitlab_project(remote: &GitLab) -> Result<GitLabProject> {
+    let url = &format!("{}/projects/{}", remote.api_root, remote.id);
+    match query_gitlab_api_cached(remote, url) {
+        Ok(buf) => Ok(serde_json::from_value(buf).expect("failed to decode response")),
+        Err(response) => {
+            debug!("Failed project query response: {:?}", response);
+            if response.status() == 404 {
+                return Err(anyhow!("remote project not found"));
+            }
+            Err(anyhow!("failed to read response"))
+        }
+    }
+}
+
+/// Open a merge request against the remote's project
+fn create_gitlab_merge_request(remote: &GitLab, new_req: &NewRequest) -> Result<MergeRequest> {
+    trace!("Creating GitLab MR {:?}", new_req);
+    let url = &format!("{}/projects/{}/merge_requests", remote.api_root, remote.id);
+    // Drafts are marked by their title
+    let title = if new_req.draft {
+        format!("Draft: {}", new_req.title)
+    } else {
+        new_req.title.clone()
+    };
+    let body = serde_json::json!({
+        "title": title,
+        "description": new_req.description,
+        "source_branch": new_req.source_branch,
+        "target_branch": new_req.target_branch,
+    });
+    match send_gitlab_api("POST", url, &remote.api_key, body) {
+        Ok(buf) => Ok(gitlab_to_mr(
+            serde_json::from_value(buf).expect("failed to decode response"),
+        )),
+        Err(response) => {
+            debug!("Failed MR creation response: {:?}", response);
+            match response.status() {
+                404 => Err(anyhow!("remote project not found")),
+                // GitLab rejects duplicate MRs as a conflict
+                409 => Err(anyhow!("there's already a request for this branch")),
+                _ => Err(anyhow!("failed to read response")),
+            }
+        }
+    }
+}
+
 /// Query the GitLab API for remote's project
 fn query_gitlab_project_id(remote: &GitLab) -> Result<i64> {
     trace!("Querying GitLab Project API for {:?}", remote);
@@ -129,35 +489,129 @@ fn query_gitlab_project_id(remote: &GitLab) -> Result<i64> {
     Ok(buf.id)
 }
 
+/// Get the status of the MR's head pipeline
+fn gitlab_pipeline_status(req: &GitLabMergeRequest) -> PipelineStatus {
+    match req
+        .head_pipeline
+        .as_ref()
+        .map(|pipeline| pipeline.status.as_ref())
+    {
+        Some("success") => PipelineStatus::Passed,
+        Some("failed") | Some("canceled") => PipelineStatus::Failed,
+        Some("created")
+        | Some("waiting_for_resource")
+        | Some("preparing")
+        | Some("pending")
+        | Some("running")
+        | Some("scheduled") => PipelineStatus::Running,
+        _ => PipelineStatus::None,
+    }
+}
+
 /// Convert a GitLab MR to a git-req MR
 fn gitlab_to_mr(req: GitLabMergeRequest) -> MergeRequest {
+    let pipeline_status = gitlab_pipeline_status(&req);
+    // The full reference looks like `group/project!iid`
+    let project = req
+        .references
+        .and_then(|references| {
+            let (path, _) = references.full.rsplit_once('!')?;
+            Some(String::from(path))
+        })
+        .unwrap_or_default();
     MergeRequest {
         id: req.iid,
+        project,
         title: req.title,
         description: req.description,
+        // GitLab is alone in calling open requests "opened"
+        state: if req.state == "opened" {
+            String::from("open")
+        } else {
+            req.state
+        },
         source_branch: req.source_branch,
+        target_branch: req.target_branch,
+        author: req.author.username,
+        labels: req.labels,
+        reviewers: req
+            .reviewers
+            .into_iter()
+            .map(|user| user.username)
+            .collect(),
+        assignees: req
+            .assignees
+            .into_iter()
+            .map(|user| user.username)
+            .collect(),
+        milestone: req.milestone.map(|milestone| milestone.title),
+        created_at: req.created_at,
+        updated_at: req.updated_at,
+        // Older GitLab instances only report the deprecated `work_in_progress` flag
+        draft: req.draft || req.work_in_progress,
+        sha: req.sha,
+        // Squashed MRs may also have a merge commit, which only merges in the squashed commit
+        merge_commit_sha: req.squash_commit_sha.or(req.merge_commit_sha),
+        web_url: req.web_url,
+        pipeline_status,
+        review_status: ReviewStatus::Unknown,
+        approved_by: vec![],
+        approved_by_me: false,
     }
 }
 
-/// Get the list of merge requests for the current project
-fn retrieve_gitlab_project_merge_requests(remote: &GitLab) -> Result<Vec<MergeRequest>> {
+/// Get the list of merge requests in the given state for the current project, optionally
+/// matching a search query or coming from the given source branch
+fn retrieve_gitlab_project_merge_requests(
+    remote: &GitLab,
+    state: &str,
+    search: Option<&str>,
+    source_branch: Option<&str>,
+) -> Result<Vec<MergeRequest>> {
     trace!("Querying GitLab MR for {:?}", remote);
-    let current_page = 1;
-    let url = &format!(
-        "{}/projects/{}/merge_requests?state=opened&per_page=50&page={}",
-        remote.api_root, remote.id, current_page,
+    let mut url = format!(
+        "{}/projects/{}/merge_requests?state={}",
+        remote.api_root, remote.id, state,
     );
-    let resp = query_gitlab_api(url, &remote.api_key);
+    if let Some(query) = search {
+        trace!("Searching for MRs matching {:?}", query);
+        url.push_str(&format!("&search={}", encode_query_value(query)));
+    }
+    if let Some(branch) = source_branch {
+        trace!("Filtering MRs by source branch {:?}", branch);
+        url.push_str(&format!("&source_branch={}", encode_query_value(branch)));
+    }
+    query_all_gitlab_merge_requests(remote, &url, "remote project not found")
+}
+
+/// Get the list of merge requests for every project in the remote's group
+fn retrieve_gitlab_group_merge_requests(remote: &GitLab) -> Result<Vec<MergeRequest>> {
+    trace!("Querying GitLab group MRs for {:?}", remote);
+    let namespace = query_gitlab_namespace(remote)?;
+    if namespace.kind != "group" {
+        return Err(anyhow!("'{}' isn't a group", namespace.full_path));
+    }
+    let url = format!(
+        "{}/groups/{}/merge_requests?state=opened",
+        remote.api_root, namespace.id,
+    );
+    query_all_gitlab_merge_requests(remote, &url, "remote group not found")
+}
+
+/// Query the GitLab API for a list of merge requests
+fn query_gitlab_merge_requests(
+    remote: &GitLab,
+    url: &str,
+    not_found: &str,
+) -> Result<Vec<MergeRequest>> {
+    let resp = query_gitlab_api_cached(remote, url);
     debug!("MR list query response: {:?}", resp);
     let merge_requests: Vec<GitLabMergeRequest> = match resp {
-        Ok(response) => {
-            let buf = response.into_json().expect("malformed API response");
-            serde_json::from_value(buf).expect("failed to decode response")
-        }
+        Ok(buf) => serde_json::from_value(buf).expect("failed to decode response"),
         Err(response) => {
             debug!("Failed MR list query response: {:?}", response);
             if response.status() == 404 {
-                return Err(anyhow!("remote project not found"));
+                return Err(anyhow!("{}", not_found));
             }
             return Err(anyhow!("failed to read response"));
         }
@@ -165,29 +619,54 @@ fn retrieve_gitlab_project_merge_requests(remote: &GitLab) -> Result<Vec<MergeRe
     Ok(merge_requests.into_iter().map(gitlab_to_mr).collect())
 }
 
-/// Search GitLab for the project ID (if the direct lookup didn't work)
-fn search_gitlab_project_id(remote: &GitLab) -> Result<i64> {
-    trace!(
-        "Searching GitLab API for namespace {:?} by project name",
-        remote.namespace
-    );
+/// The number of results to ask for per page when reading every page of a list
+const GITLAB_PAGE_SIZE: usize = 100;
+
+/// Query the GitLab API for every page of a list of merge requests
+fn query_all_gitlab_merge_requests(
+    remote: &GitLab,
+    url: &str,
+    not_found: &str,
+) -> Result<Vec<MergeRequest>> {
+    let mut merge_requests = vec![];
+    for page in 1.. {
+        let page_url = format!("{}&per_page={}&page={}", url, GITLAB_PAGE_SIZE, page);
+        let page_mrs = query_gitlab_merge_requests(remote, &page_url, not_found)?;
+        let is_last_page = page_mrs.len() < GITLAB_PAGE_SIZE;
+        merge_requests.extend(page_mrs);
+        if is_last_page {
+            break;
+        }
+    }
+    Ok(merge_requests)
+}
+
+/// Query the GitLab API for the namespace (user or group) that owns the remote's project
+fn query_gitlab_namespace(remote: &GitLab) -> Result<GitLabNamespace> {
     let url = &format!("{}/namespaces/{}", remote.api_root, remote.namespace);
     let resp = query_gitlab_api(url, &remote.api_key);
     debug!("Namespace ID query response: {:?}", resp);
-    let ns_buf: GitLabNamespace = match resp {
+    match resp {
         Ok(response) => match response.into_json() {
-            Ok(buf) => serde_json::from_value(buf).expect("failed to decode response"),
-            Err(_) => {
-                return Err(anyhow!("malformed response received"));
-            }
+            Ok(buf) => Ok(serde_json::from_value(buf).expect("failed to decode response")),
+            Err(_) => Err(anyhow!("malformed response received")),
         },
         Err(response) => {
             if response.status() == 404 {
                 return Err(anyhow!("couldn't find namespace"));
             }
-            return Err(anyhow!("failed to read response"));
+            Err(anyhow!("failed to read response"))
         }
-    };
+    }
+}
+
+/// Search GitLab for the project ID (if the direct lookup didn't work)
+fn search_gitlab_project_id(remote: &GitLab) -> Result<i64> {
+    trace!(
+        "Searching GitLab API for namespace {:?} by project name",
+        remote.namespace
+    );
+    let ns_buf = query_gitlab_namespace(remote)?;
     debug!("Querying namespace {:?}", ns_buf);
     let url = match ns_buf.kind.as_ref() {
         "user" => format!("{}/users/{}/projects", remote.api_root, ns_buf.id),
@@ -222,21 +701,500 @@ pub fn load_project_id(remote_name: &str) -> Option<String> {
     git::get_config("projectid", remote_name).debug_none("No project ID found")
 }
 
-/// Query the GitLab API for the branch corresponding to the MR
-fn query_gitlab_branch_name(remote: &GitLab, mr_id: i64) -> Result<String> {
+/// Get the project ID to query for the MR, which may belong to another project in the group
+fn get_gitlab_req_project(remote: &GitLab, req: &MergeRequest) -> String {
+    if req.project.is_empty() || req.project == remote.full_path {
+        return remote.id.clone();
+    }
+    req.project.replace('/', "%2F")
+}
+
+/// Query the GitLab API for the MR with the given ID
+fn query_gitlab_merge_request(
+    remote: &GitLab,
+    project: &str,
+    mr_id: i64,
+) -> Result<GitLabMergeRequest> {
     let url = &format!(
         "{}/projects/{}/merge_requests/{}",
-        remote.api_root, remote.id, mr_id
+        remote.api_root, project, mr_id
     );
-    let resp = ureq::get(url).set("PRIVATE-TOKEN", &remote.api_key).call();
+    let resp = query_gitlab_api_cached(remote, url);
     debug!("Response: {:?}", resp);
-    let buf: GitLabMergeRequest = match resp.into_json() {
+    match resp {
+        Ok(buf) => Ok(serde_json::from_value(buf).expect("failed to decode response")),
+        Err(response) => {
+            if response.status() == 404 {
+                return Err(anyhow!("merge request not found"));
+            }
+            Err(anyhow!("failed to read response"))
+        }
+    }
+}
+
+/// Get an MR's commits as an mbox of patches. GitLab only serves these from the MR's web page,
+/// rather than the API.
+fn retrieve_gitlab_patches(remote: &GitLab, mr_id: i64) -> Result<String> {
+    let mr = query_gitlab_merge_request(remote, &remote.id, mr_id)?;
+    let url = &format!("{}.patch", mr.web_url);
+    trace!("Querying {} for patches", url);
+    let response = ureq::get(url).set("PRIVATE-TOKEN", &remote.api_key).call();
+    if response.error() {
+        debug!("Failed patch query response: {:?}", response);
+        return match response.status() {
+            401 | 403 | 404 => Err(anyhow!("the merge request's patches aren't accessible")),
+            _ => Err(anyhow!("failed to read response")),
+        };
+    }
+    let patches = response.into_string()?;
+    // Projects that need signing in redirect to a login page rather than failing
+    if !patches.is_empty() && !patches.starts_with("From ") {
+        return Err(anyhow!("the merge request's patches aren't accessible"));
+    }
+    Ok(patches)
+}
+
+/// Query the GitLab API for the user that owns the API key
+fn query_gitlab_current_user(remote: &GitLab) -> Result<GitLabUser> {
+    let url = &format!("{}/user", remote.api_root);
+    match query_gitlab_api_cached(remote, url) {
+        Ok(buf) => Ok(serde_json::from_value(buf).expect("failed to decode response")),
+        Err(response) => {
+            debug!("Failed user query response: {:?}", response);
+            Err(anyhow!("failed to read response"))
+        }
+    }
+}
+
+/// Query the GitLab API for the approval state of the MR
+fn query_gitlab_approvals(remote: &GitLab, project: &str, mr_id: i64) -> Result<GitLabApprovals> {
+    let url = &format!(
+        "{}/projects/{}/merge_requests/{}/approvals",
+        remote.api_root, project, mr_id
+    );
+    match query_gitlab_api_cached(remote, url) {
+        Ok(buf) => Ok(serde_json::from_value(buf).expect("failed to decode response")),
+        Err(response) => {
+            debug!("Failed approvals query response: {:?}", response);
+            Err(anyhow!("failed to read response"))
+        }
+    }
+}
+
+/// Approve or unapprove the MR as the current user
+fn post_gitlab_approval(remote: &GitLab, mr_id: i64, action: &str) -> Result<()> {
+    let url = &format!(
+        "{}/projects/{}/merge_requests/{}/{}",
+        remote.api_root, remote.id, mr_id, action
+    );
+    match send_gitlab_api("POST", url, &remote.api_key, serde_json::json!({})) {
+        Ok(_) => Ok(()),
+        Err(response) => {
+            debug!("Failed {} response: {:?}", action, response);
+            match response.status() {
+                401 => Err(anyhow!(
+                    "you aren't allowed to {} this merge request",
+                    action
+                )),
+                404 => Err(anyhow!("merge request not found, or not approved by you")),
+                _ => Err(anyhow!("failed to read response")),
+            }
+        }
+    }
+}
+
+/// Apply the changes to the MR
+fn update_gitlab_merge_request(
+    remote: &GitLab,
+    mr_id: i64,
+    update: &ReqUpdate,
+) -> Result<MergeRequest> {
+    let url = &format!(
+        "{}/projects/{}/merge_requests/{}",
+        remote.api_root, remote.id, mr_id
+    );
+    let mut changes = serde_json::json!({});
+    if let Some(state) = update.state {
+        changes["state_event"] = serde_json::Value::from(match state {
+            StateChange::Close => "close",
+            StateChange::Reopen => "reopen",
+        });
+    }
+    if let Some(target_branch) = &update.target_branch {
+        changes["target_branch"] = serde_json::Value::from(target_branch.as_str());
+    }
+    // Drafts are marked by their title, so changing either needs the other
+    if update.title.is_some() || update.draft.is_some() {
+        let current = match (&update.title, update.draft) {
+            (Some(_), Some(_)) => None,
+            _ => Some(gitlab_to_mr(query_gitlab_merge_request(
+                remote, &remote.id, mr_id,
+            )?)),
+        };
+        let title = update
+            .title
+            .as_deref()
+            .or(current.as_ref().map(|mr| mr.title.as_str()))
+            .unwrap_or_default();
+        let draft = update
+            .draft
+            .or(current.as_ref().map(|mr| mr.draft))
+            .unwrap_or_default();
+        changes["title"] = serde_json::Value::from(format_gitlab_title(title, draft));
+    }
+    if let Some(description) = &update.description {
+        changes["description"] = serde_json::Value::from(description.as_str());
+    }
+    if let Some(labels) = &update.labels {
+        changes["labels"] = serde_json::Value::from(labels.join(","));
+    }
+    if let Some(reviewers) = &update.reviewers {
+        changes["reviewer_ids"] =
+            serde_json::Value::from(query_gitlab_user_ids(remote, reviewers)?);
+    }
+    if let Some(assignees) = &update.assignees {
+        changes["assignee_ids"] =
+            serde_json::Value::from(query_gitlab_user_ids(remote, assignees)?);
+    }
+    if let Some(milestone) = &update.milestone {
+        // A milestone ID of 0 removes the milestone
+        let milestone_id = match milestone {
+            Some(title) => query_gitlab_milestone_id(remote, title)?,
+            None => 0,
+        };
+        changes["milestone_id"] = serde_json::Value::from(milestone_id);
+    }
+    match send_gitlab_api("PUT", url, &remote.api_key, changes) {
+        Ok(buf) => {
+            let req: GitLabMergeRequest =
+                serde_json::from_value(buf).expect("failed to decode response");
+            Ok(gitlab_to_mr(req))
+        }
+        Err(response) => {
+            debug!("Failed MR update response: {:?}", response);
+            match response.status() {
+                400 => Err(anyhow!(
+                    "the changes were rejected; check the target branch exists"
+                )),
+                401 | 403 => Err(anyhow!("you aren't allowed to change this merge request")),
+                404 => Err(anyhow!("merge request not found")),
+                _ => Err(anyhow!("failed to read response")),
+            }
+        }
+    }
+}
+
+/// The title prefixes GitLab treats as marking a draft
+const GITLAB_DRAFT_PREFIXES: [&str; 5] = ["draft:", "[draft]", "(draft)", "wip:", "[wip]"];
+
+/// Remove any draft markers from the start of the title, then add one back if it's a draft
+fn format_gitlab_title(title: &str, draft: bool) -> String {
+    let mut title = title.trim_start();
+    while let Some(prefix) = GITLAB_DRAFT_PREFIXES.iter().find(|prefix| {
+        title
+            .get(..prefix.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+    }) {
+        title = title[prefix.len()..].trim_start();
+    }
+    if draft {
+        format!("Draft: {}", title)
+    } else {
+        String::from(title)
+    }
+}
+
+/// Look up the IDs of the users with the given usernames
+fn query_gitlab_user_ids(remote: &GitLab, usernames: &[String]) -> Result<Vec<i64>> {
+    usernames
+        .iter()
+        .map(|username| {
+            let url = &format!(
+                "{}/users?username={}",
+                remote.api_root,
+                encode_query_value(username)
+            );
+            let users: Vec<GitLabUser> = match query_gitlab_api_cached(remote, url) {
+                Ok(buf) => serde_json::from_value(buf).expect("failed to decode response"),
+                Err(response) => {
+                    debug!("Failed user lookup response: {:?}", response);
+                    return Err(anyhow!("failed to read response"));
+                }
+            };
+            users
+                .first()
+                .map(|user| user.id)
+                .ok_or_else(|| anyhow!("there's no user named {}", username))
+        })
+        .collect()
+}
+
+/// Look up the ID of the project's milestone with the given title
+fn query_gitlab_milestone_id(remote: &GitLab, title: &str) -> Result<i64> {
+    let url = &format!(
+        "{}/projects/{}/milestones?title={}",
+        remote.api_root,
+        remote.id,
+        encode_query_value(title)
+    );
+    let milestones: Vec<GitLabMilestone> = match query_gitlab_api_cached(remote, url) {
         Ok(buf) => serde_json::from_value(buf).expect("failed to decode response"),
-        Err(_) => {
+        Err(response) => {
+            debug!("Failed milestone lookup response: {:?}", response);
             return Err(anyhow!("failed to read response"));
         }
     };
-    Ok(buf.source_branch)
+    milestones
+        .iter()
+        .find(|milestone| milestone.title == title)
+        .map(|milestone| milestone.id)
+        .ok_or_else(|| anyhow!("there's no milestone named {}", title))
+}
+
+/// Merge the MR, or set it to be merged when its pipeline succeeds
+fn merge_gitlab_merge_request(
+    remote: &GitLab,
+    mr_id: i64,
+    head_sha: &str,
+    options: &MergeOptions,
+) -> Result<MergeOutcome> {
+    let squash = match options.method {
+        MergeMethod::Merge => false,
+        MergeMethod::Squash => true,
+        MergeMethod::Rebase => {
+            return Err(anyhow!(
+                "GitLab projects choose between merge commits and fast-forward merges in their \
+                 settings; use the merge or squash method"
+            ))
+        }
+    };
+    let url = &format!(
+        "{}/projects/{}/merge_requests/{}/merge",
+        remote.api_root, remote.id, mr_id
+    );
+    // GitLab refuses to merge if the source branch has moved on from the given commit
+    let mut merge = serde_json::json!({
+        "sha": head_sha,
+        "squash": squash,
+        "should_remove_source_branch": options.delete_branch,
+        "merge_when_pipeline_succeeds": options.auto,
+    });
+    if let Some(message) = &options.message {
+        let field = if squash {
+            "squash_commit_message"
+        } else {
+            "merge_commit_message"
+        };
+        merge[field] = serde_json::Value::from(message.as_str());
+    }
+    match send_gitlab_api("PUT", url, &remote.api_key, merge) {
+        Ok(buf) => {
+            let merged: GitLabMergeRequest =
+                serde_json::from_value(buf).expect("failed to decode response");
+            if merged.state == "merged" {
+                Ok(MergeOutcome::Merged)
+            } else if merged.merge_when_pipeline_succeeds {
+                Ok(MergeOutcome::Scheduled)
+            } else {
+                Err(anyhow!("the merge request is still {}", merged.state))
+            }
+        }
+        Err(response) => {
+            debug!("Failed merge response: {:?}", response);
+            match response.status() {
+                401 => Err(anyhow!("you aren't allowed to merge this merge request")),
+                404 => Err(anyhow!("merge request not found")),
+                405 => Err(anyhow!(
+                    "the merge request can't be merged yet; it may be a draft, have unresolved \
+                     discussions, or be waiting on its pipeline or approvals"
+                )),
+                406 => Err(anyhow!(
+                    "the merge request has conflicts or is already merged"
+                )),
+                409 => Err(anyhow!(
+                    "the source branch has changed since {}; review the new commits first",
+                    head_sha
+                )),
+                _ => Err(anyhow!("failed to read response")),
+            }
+        }
+    }
+}
+
+/// Query the GitLab API for the MR's discussion threads, oldest first
+fn query_gitlab_discussions(remote: &GitLab, mr_id: i64) -> Result<Vec<GitLabDiscussion>> {
+    let mut discussions = vec![];
+    for page in 1.. {
+        let url = &format!(
+            "{}/projects/{}/merge_requests/{}/discussions?per_page={}&page={}",
+            remote.api_root, remote.id, mr_id, GITLAB_PAGE_SIZE, page
+        );
+        let page_discussions: Vec<GitLabDiscussion> = match query_gitlab_api_cached(remote, url) {
+            Ok(buf) => serde_json::from_value(buf).expect("failed to decode response"),
+            Err(response) => {
+                debug!("Failed discussions query response: {:?}", response);
+                if response.status() == 404 {
+                    return Err(anyhow!("merge request not found"));
+                }
+                return Err(anyhow!("failed to read response"));
+            }
+        };
+        let is_last_page = page_discussions.len() < GITLAB_PAGE_SIZE;
+        discussions.extend(page_discussions);
+        if is_last_page {
+            break;
+        }
+    }
+    Ok(discussions)
+}
+
+/// Leave a note on the MR as a whole
+fn post_gitlab_note(remote: &GitLab, mr_id: i64, body: &str) -> Result<Comment> {
+    let url = &format!(
+        "{}/projects/{}/merge_requests/{}/notes",
+        remote.api_root, remote.id, mr_id
+    );
+    match send_gitlab_api(
+        "POST",
+        url,
+        &remote.api_key,
+        serde_json::json!({ "body": body }),
+    ) {
+        Ok(buf) => {
+            let note: GitLabNote = serde_json::from_value(buf).expect("failed to decode response");
+            Ok(gitlab_to_comment(note))
+        }
+        Err(response) => {
+            debug!("Failed note creation response: {:?}", response);
+            match response.status() {
+                401 | 403 => Err(anyhow!(
+                    "you aren't allowed to comment on this merge request"
+                )),
+                404 => Err(anyhow!("merge request not found")),
+                _ => Err(anyhow!("failed to read response")),
+            }
+        }
+    }
+}
+
+/// Build the position of a diff note. Unchanged lines need their number on both sides.
+fn gitlab_position(diff_refs: &GitLabDiffRefs, position: &DiffPosition) -> serde_json::Value {
+    let mut gitlab_position = serde_json::json!({
+        "position_type": "text",
+        "base_sha": diff_refs.base_sha,
+        "start_sha": diff_refs.start_sha,
+        "head_sha": diff_refs.head_sha,
+        "old_path": position.old_path,
+        "new_path": position.new_path,
+        "new_line": position.new_line,
+    });
+    if let Some(old_line) = position.old_line {
+        gitlab_position["old_line"] = serde_json::Value::from(old_line);
+    }
+    gitlab_position
+}
+
+/// Start a discussion on a line of the MR's diff
+fn post_gitlab_discussion(
+    remote: &GitLab,
+    mr_id: i64,
+    body: &str,
+    position: serde_json::Value,
+) -> Result<Comment> {
+    let url = &format!(
+        "{}/projects/{}/merge_requests/{}/discussions",
+        remote.api_root, remote.id, mr_id
+    );
+    let discussion = serde_json::json!({ "body": body, "position": position });
+    match send_gitlab_api("POST", url, &remote.api_key, discussion) {
+        Ok(buf) => {
+            let discussion: GitLabDiscussion =
+                serde_json::from_value(buf).expect("failed to decode response");
+            discussion
+                .notes
+                .into_iter()
+                .next()
+                .map(gitlab_to_comment)
+                .ok_or_else(|| anyhow!("the discussion was created without a note"))
+        }
+        Err(response) => {
+            debug!("Failed discussion creation response: {:?}", response);
+            match response.status() {
+                // GitLab rejects positions outside of the diff
+                400 => Err(anyhow!(
+                    "the line couldn't be found in the merge request's diff"
+                )),
+                401 | 403 => Err(anyhow!(
+                    "you aren't allowed to comment on this merge request"
+                )),
+                404 => Err(anyhow!("merge request not found")),
+                _ => Err(anyhow!("failed to read response")),
+            }
+        }
+    }
+}
+
+/// Convert a GitLab note to a git-req comment
+fn gitlab_to_comment(note: GitLabNote) -> Comment {
+    Comment {
+        id: note.id,
+        author: note.author.username,
+        body: note.body,
+        created_at: note.created_at,
+    }
+}
+
+/// Convert a GitLab discussion to a git-req discussion, skipping the ones GitLab generates itself
+/// (e.g. "added 1 commit")
+fn gitlab_to_discussion(discussion: GitLabDiscussion) -> Option<Discussion> {
+    let first = discussion.notes.first()?;
+    if first.system {
+        return None;
+    }
+    let resolved = first.resolvable.then_some(first.resolved);
+    let location = first.position.as_ref().map(|position| CommentLocation {
+        path: position
+            .new_path
+            .clone()
+            .or_else(|| position.old_path.clone())
+            .unwrap_or_default(),
+        line: position.new_line.or(position.old_line),
+        diff_hunk: None,
+    });
+    Some(Discussion {
+        id: discussion.id,
+        location,
+        resolved,
+        comments: discussion
+            .notes
+            .into_iter()
+            .filter(|note| !note.system)
+            .map(gitlab_to_comment)
+            .collect(),
+    })
+}
+
+/// Query the GitLab API for the versions of the MR's diff, newest first
+fn query_gitlab_versions(remote: &GitLab, mr_id: i64) -> Result<Vec<GitLabVersion>> {
+    let url = &format!(
+        "{}/projects/{}/merge_requests/{}/versions",
+        remote.api_root, remote.id, mr_id
+    );
+    match query_gitlab_api_cached(remote, url) {
+        Ok(buf) => Ok(serde_json::from_value(buf).expect("failed to decode response")),
+        Err(response) => {
+            debug!("Failed versions query response: {:?}", response);
+            if response.status() == 404 {
+                return Err(anyhow!("merge request not found"));
+            }
+            Err(anyhow!("failed to read response"))
+        }
+    }
+}
+
+/// Query the GitLab API for the branch corresponding to the MR
+fn query_gitlab_branch_name(remote: &GitLab, mr_id: i64) -> Result<String> {
+    Ok(query_gitlab_merge_request(remote, &remote.id, mr_id)?.source_branch)
 }
 
 /// Extract the project name from a GitLab origin URL
@@ -276,6 +1234,7 @@ pub fn get_gitlab_project_full_path(origin: &str) -> Option<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::remotes::testing::{gitlab_mr_json, make_gitlab_remote, MockResponse, MockServer};
 
     #[test]
     fn test_get_gitlab_project_namespace_http() {
@@ -381,4 +1340,461 @@ mod tests {
         assert!(ns.is_some());
         assert_eq!("my_namespace/my_org/my_project", ns.unwrap());
     }
+
+    #[test]
+    fn test_retrieve_gitlab_group_merge_requests() {
+        let server = MockServer::start();
+        server.respond(
+            "GET",
+            "/namespaces/my_namespace",
+            MockResponse::json(
+                r#"{"id": 7, "name": "My Namespace", "path": "my_namespace", "kind": "group",
+                    "full_path": "my_namespace"}"#,
+            ),
+        );
+        let mr = gitlab_mr_json(serde_json::json!({
+            "id": 30, "iid": 3, "title": "Fix the other project", "source_branch": "fix",
+            "sha": "abc3",
+            "web_url": "https://gitlab.example.com/my_namespace/other_project/-/merge_requests/3",
+        }));
+        let mut listed_mr = mr.clone();
+        listed_mr["references"] = serde_json::json!({"full": "my_namespace/other_project!3"});
+        server.respond(
+            "GET",
+            "/groups/7/merge_requests",
+            MockResponse::json(&format!("[{}]", listed_mr)),
+        );
+        let mut full_mr = mr;
+        full_mr["head_pipeline"] = serde_json::json!({"id": 1, "status": "failed"});
+        server.respond(
+            "GET",
+            "/projects/my_namespace%2Fother_project/merge_requests/3",
+            MockResponse::json(&full_mr.to_string()),
+        );
+        let mut remote = make_gitlab_remote(&server);
+        let mut reqs = remote.get_group_req_names().unwrap().reqs;
+        assert_eq!(1, reqs.len());
+        assert_eq!("my_namespace/other_project", reqs[0].project);
+        remote.populate_pipeline_statuses(&mut reqs);
+        assert_eq!(PipelineStatus::Failed, reqs[0].pipeline_status);
+    }
+
+    #[test]
+    fn test_retrieve_gitlab_group_merge_requests_pages() {
+        let server = MockServer::start();
+        server.respond(
+            "GET",
+            "/namespaces/my_namespace",
+            MockResponse::json(
+                r#"{"id": 7, "name": "My Namespace", "path": "my_namespace", "kind": "group",
+                    "full_path": "my_namespace"}"#,
+            ),
+        );
+        let page = |count: usize| {
+            let mrs: Vec<serde_json::Value> = (1..=count)
+                .map(|iid| gitlab_mr_json(serde_json::json!({ "iid": iid })))
+                .collect();
+            MockResponse::json(&serde_json::Value::from(mrs).to_string())
+        };
+        let path = "/groups/7/merge_requests";
+        server.respond("GET", path, page(GITLAB_PAGE_SIZE));
+        server.respond("GET", path, page(GITLAB_PAGE_SIZE));
+        server.respond("GET", path, page(0));
+        let mut remote = make_gitlab_remote(&server);
+        let reqs = remote.get_group_req_names().unwrap().reqs;
+        assert_eq!(2 * GITLAB_PAGE_SIZE, reqs.len());
+        let requests = server.requests_to(path);
+        assert_eq!(3, requests.len());
+        assert!(requests[2].query.contains("page=3"));
+    }
+
+    #[test]
+    fn test_get_req_revisions() {
+        let server = MockServer::start();
+        server.respond(
+            "GET",
+            "/projects/1/merge_requests/5/versions",
+            MockResponse::json(
+                r#"[{"id": 2, "head_commit_sha": "bbb", "base_commit_sha": "aaa",
+                     "start_commit_sha": "aaa", "created_at": "2020-01-02T00:00:00.000Z",
+                     "merge_request_id": 50, "state": "collected", "real_size": "1"},
+                    {"id": 1, "head_commit_sha": "abc", "base_commit_sha": "aaa",
+                     "start_commit_sha": "aaa", "created_at": "2020-01-01T00:00:00.000Z",
+                     "merge_request_id": 50, "state": "collected", "real_size": "1"}]"#,
+            ),
+        );
+        let mut remote = make_gitlab_remote(&server);
+        let revisions = remote.get_req_revisions(5).unwrap();
+        assert_eq!(
+            vec!["bbb", "abc"],
+            revisions
+                .iter()
+                .map(|revision| revision.head_sha.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("aaa", revisions[1].base_sha);
+    }
+
+    #[test]
+    fn test_find_reqs_by_commit() {
+        let server = MockServer::start();
+        let mr = gitlab_mr_json(serde_json::json!({
+            "id": 30, "iid": 3, "title": "Fix the bug", "state": "merged",
+            "source_branch": "fix", "sha": "abc3",
+            "web_url": "https://gitlab.example.com/my_namespace/my_project/-/merge_requests/3",
+        }));
+        server.respond(
+            "GET",
+            "/projects/1/repository/commits/abc3/merge_requests",
+            MockResponse::json(&format!("[{}]", mr)),
+        );
+        let mut remote = make_gitlab_remote(&server);
+        let reqs = remote.find_reqs_by_commit("abc3").unwrap();
+        assert_eq!(vec![3], reqs.iter().map(|req| req.id).collect::<Vec<_>>());
+        assert_eq!("merged", reqs[0].state);
+        assert!(remote.find_reqs_by_commit("def4").is_err());
+    }
+
+    #[test]
+    fn test_get_discussions_pages() {
+        let server = MockServer::start();
+        let discussion = |id: usize| {
+            serde_json::json!({"id": format!("d{}", id), "individual_note": false, "notes": [
+                {"id": id, "body": "Why?", "system": false,
+                 "resolvable": true, "resolved": false,
+                 "author": {"id": 2, "username": "reviewer"},
+                 "created_at": "2020-01-02T00:00:00.000Z"}]})
+        };
+        let page = |ids: std::ops::RangeInclusive<usize>| {
+            let discussions: Vec<serde_json::Value> = ids.map(discussion).collect();
+            MockResponse::json(&serde_json::Value::Array(discussions).to_string())
+        };
+        let path = "/projects/1/merge_requests/5/discussions";
+        server.respond("GET", path, page(1..=GITLAB_PAGE_SIZE));
+        server.respond(
+            "GET",
+            path,
+            page(GITLAB_PAGE_SIZE + 1..=GITLAB_PAGE_SIZE + 1),
+        );
+        let mut remote = make_gitlab_remote(&server);
+        let discussions = remote.get_discussions(5).unwrap();
+        assert_eq!(GITLAB_PAGE_SIZE + 1, discussions.len());
+        let requests = server.requests_to(path);
+        assert_eq!(2, requests.len());
+        assert!(requests[1].query.contains("page=2"));
+    }
+
+    #[test]
+    fn test_get_discussions() {
+        let server = MockServer::start();
+        server.respond(
+            "GET",
+            "/projects/1/merge_requests/5/discussions",
+            MockResponse::json(
+                r#"[{"id": "a1", "individual_note": true, "notes": [
+                        {"id": 1, "body": "added 1 commit", "system": true,
+                         "author": {"id": 1, "username": "aru"},
+                         "created_at": "2020-01-01T00:00:00.000Z"}]},
+                    {"id": "b2", "individual_note": false, "notes": [
+                        {"id": 2, "body": "Off by one?", "system": false,
+                         "resolvable": true, "resolved": false,
+                         "author": {"id": 2, "username": "reviewer"},
+                         "created_at": "2020-01-02T00:00:00.000Z",
+                         "position": {"new_path": "src/git.rs", "old_path": "src/git.rs",
+                                      "new_line": 120, "old_line": null}},
+                        {"id": 3, "body": "Fixed", "system": false,
+                         "resolvable": true, "resolved": false,
+                         "author": {"id": 1, "username": "aru"},
+                         "created_at": "2020-01-03T00:00:00.000Z"}]}]"#,
+            ),
+        );
+        let mut remote = make_gitlab_remote(&server);
+        let discussions = remote.get_discussions(5).unwrap();
+        assert_eq!(1, discussions.len());
+        assert_eq!(Some(false), discussions[0].resolved);
+        assert_eq!(
+            Some(CommentLocation {
+                path: String::from("src/git.rs"),
+                line: Some(120),
+                diff_hunk: None,
+            }),
+            discussions[0].location
+        );
+        assert_eq!(
+            vec!["reviewer", "aru"],
+            discussions[0]
+                .comments
+                .iter()
+                .map(|comment| comment.author.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_gitlab_position() {
+        let diff_refs = GitLabDiffRefs {
+            base_sha: String::from("base"),
+            head_sha: String::from("head"),
+            start_sha: String::from("start"),
+        };
+        let added = DiffPosition {
+            old_path: String::from("src/git.rs"),
+            new_path: String::from("src/git.rs"),
+            old_line: None,
+            new_line: 14,
+        };
+        let position = gitlab_position(&diff_refs, &added);
+        assert_eq!("start", position["start_sha"]);
+        assert_eq!(14, position["new_line"]);
+        assert!(position.get("old_line").is_none());
+        let context = DiffPosition {
+            old_line: Some(13),
+            ..added
+        };
+        assert_eq!(13, gitlab_position(&diff_refs, &context)["old_line"]);
+    }
+
+    fn merge_response(state: &str, merge_when_pipeline_succeeds: bool) -> MockResponse {
+        let mr = gitlab_mr_json(serde_json::json!({
+            "state": state,
+            "merge_when_pipeline_succeeds": merge_when_pipeline_succeeds,
+        }));
+        MockResponse::json(&mr.to_string())
+    }
+
+    fn make_merge_options(method: MergeMethod, auto: bool) -> MergeOptions {
+        MergeOptions {
+            method,
+            auto,
+            delete_branch: false,
+            message: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_req() {
+        let server = MockServer::start();
+        let path = "/projects/1/merge_requests/5/merge";
+        server.respond("PUT", path, merge_response("merged", false));
+        server.respond("PUT", path, merge_response("opened", true));
+        server.respond("PUT", path, MockResponse::status(409));
+        let mut remote = make_gitlab_remote(&server);
+        let options = make_merge_options(MergeMethod::Squash, false);
+        assert_eq!(
+            MergeOutcome::Merged,
+            remote.merge_req(5, "abc", &options).unwrap()
+        );
+        let options = make_merge_options(MergeMethod::Merge, true);
+        assert_eq!(
+            MergeOutcome::Scheduled,
+            remote.merge_req(5, "abc", &options).unwrap()
+        );
+        assert!(remote.merge_req(5, "abc", &options).is_err());
+        assert_eq!(3, server.requests_to(path).len());
+    }
+
+    #[test]
+    fn test_merge_req_rebase() {
+        let server = MockServer::start();
+        let mut remote = make_gitlab_remote(&server);
+        let options = make_merge_options(MergeMethod::Rebase, false);
+        assert!(remote.merge_req(5, "abc", &options).is_err());
+    }
+
+    #[test]
+    fn test_update_req() {
+        let server = MockServer::start();
+        let mr = gitlab_mr_json(serde_json::json!({"target_branch": "release-1.3"}));
+        server.respond(
+            "PUT",
+            "/projects/1/merge_requests/5",
+            MockResponse::json(&mr.to_string()),
+        );
+        let mut remote = make_gitlab_remote(&server);
+        let update = ReqUpdate {
+            target_branch: Some(String::from("release-1.3")),
+            ..ReqUpdate::default()
+        };
+        let mr = remote.update_req(5, &update).unwrap();
+        assert_eq!("open", mr.state);
+        assert_eq!("release-1.3", mr.target_branch);
+        assert_eq!(1, server.requests_to("/projects/1/merge_requests/5").len());
+    }
+
+    #[test]
+    fn test_format_gitlab_title() {
+        assert_eq!("Draft: Add edit", format_gitlab_title("Add edit", true));
+        assert_eq!("Add edit", format_gitlab_title("Draft: Add edit", false));
+        assert_eq!(
+            "Draft: Add edit",
+            format_gitlab_title("[WIP] draft: Add edit", true)
+        );
+        assert_eq!("Drafting docs", format_gitlab_title("Drafting docs", false));
+    }
+
+    #[test]
+    fn test_update_req_metadata() {
+        let server = MockServer::start();
+        server.respond(
+            "GET",
+            "/users",
+            MockResponse::json(r#"[{"id": 7, "username": "reviewer"}]"#),
+        );
+        server.respond(
+            "GET",
+            "/projects/1/milestones",
+            MockResponse::json(r#"[{"id": 3, "title": "1.3"}]"#),
+        );
+        let mr = gitlab_mr_json(serde_json::json!({
+            "title": "Draft: Add edit", "description": "Details", "source_branch": "add-edit",
+            "labels": ["ui"], "reviewers": [{"id": 7, "username": "reviewer"}],
+            "milestone": {"id": 3, "title": "1.3"}, "draft": true,
+        }));
+        server.respond(
+            "PUT",
+            "/projects/1/merge_requests/5",
+            MockResponse::json(&mr.to_string()),
+        );
+        let mut remote = make_gitlab_remote(&server);
+        let update = ReqUpdate {
+            title: Some(String::from("Add edit")),
+            draft: Some(true),
+            labels: Some(vec![String::from("ui")]),
+            reviewers: Some(vec![String::from("reviewer")]),
+            milestone: Some(Some(String::from("1.3"))),
+            ..ReqUpdate::default()
+        };
+        let mr = remote.update_req(5, &update).unwrap();
+        assert!(mr.draft);
+        assert_eq!(Some(String::from("1.3")), mr.milestone);
+        assert_eq!(vec!["reviewer"], mr.reviewers);
+        assert_eq!(1, server.requests_to("/users").len());
+        // The title and draft state were both given, so the MR wasn't queried for them
+        assert_eq!(
+            "PUT",
+            server.requests_to("/projects/1/merge_requests/5")[0].method
+        );
+    }
+
+    #[test]
+    fn test_update_req_unknown_user() {
+        let server = MockServer::start();
+        server.respond("GET", "/users", MockResponse::json("[]"));
+        let mut remote = make_gitlab_remote(&server);
+        let update = ReqUpdate {
+            assignees: Some(vec![String::from("nobody")]),
+            ..ReqUpdate::default()
+        };
+        assert!(remote.update_req(5, &update).is_err());
+        assert!(server
+            .requests_to("/projects/1/merge_requests/5")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_update_req_ready() {
+        let server = MockServer::start();
+        let path = "/projects/1/merge_requests/5";
+        let mr_json = |title: &str, draft: bool| {
+            let mr = gitlab_mr_json(serde_json::json!({
+                "title": title, "source_branch": "add-ready", "draft": draft,
+            }));
+            MockResponse::json(&mr.to_string())
+        };
+        server.respond("GET", path, mr_json("Draft: Add ready", true));
+        server.respond("PUT", path, mr_json("Add ready", false));
+        let mut remote = make_gitlab_remote(&server);
+        let update = ReqUpdate {
+            draft: Some(false),
+            ..ReqUpdate::default()
+        };
+        let mr = remote.update_req(5, &update).unwrap();
+        assert!(!mr.draft);
+        assert_eq!("Add ready", mr.title);
+        let methods: Vec<String> = server
+            .requests_to(path)
+            .into_iter()
+            .map(|request| request.method)
+            .collect();
+        assert_eq!(vec!["GET", "PUT"], methods);
+    }
+
+    #[test]
+    fn test_get_req_patches() {
+        let server = MockServer::start();
+        let patches = include_str!("../../tests/fixtures/request.patch");
+        let mr = gitlab_mr_json(serde_json::json!({
+            "title": "Greet the world", "source_branch": "greet",
+            "web_url": format!("{}/my_namespace/my_project/-/merge_requests/5", server.url),
+        }));
+        server.respond(
+            "GET",
+            "/projects/1/merge_requests/5",
+            MockResponse::json(&mr.to_string()),
+        );
+        let path = "/my_namespace/my_project/-/merge_requests/5.patch";
+        server.respond("GET", path, MockResponse::text(patches));
+        // A login page in place of the patches
+        server.respond("GET", path, MockResponse::text("<!DOCTYPE html>"));
+        let mut remote = make_gitlab_remote(&server);
+        assert_eq!(patches, remote.get_req_patches(5).unwrap());
+        assert_eq!(
+            Some("token"),
+            server.requests_to(path)[0].header("PRIVATE-TOKEN")
+        );
+        assert!(remote.get_req_patches(5).is_err());
+    }
+
+    #[test]
+    fn test_find_merged_reqs_by_source_branch() {
+        let server = MockServer::start();
+        let path = "/projects/1/merge_requests";
+        let mr = gitlab_mr_json(serde_json::json!({
+            "id": 40, "iid": 4, "title": "Add stack", "state": "merged",
+            "source_branch": "add-stack", "sha": "abc4",
+            "merge_commit_sha": "m4", "squash_commit_sha": null,
+            "web_url": "https://gitlab.example.com/my_namespace/my_project/-/merge_requests/4",
+        }));
+        server.respond("GET", path, MockResponse::json(&format!("[{}]", mr)));
+        let mut remote = make_gitlab_remote(&server);
+        let reqs = remote
+            .find_merged_reqs_by_source_branch("add-stack")
+            .unwrap();
+        assert_eq!(vec![4], reqs.iter().map(|req| req.id).collect::<Vec<_>>());
+        assert_eq!(Some("m4"), reqs[0].merge_commit_sha.as_deref());
+        let query = &server.requests_to(path)[0].query;
+        assert!(query.contains("state=merged"));
+        assert!(query.contains("source_branch=add-stack"));
+    }
+
+    #[test]
+    fn test_find_reqs_by_target_branch() {
+        let server = MockServer::start();
+        let page = |iids: std::ops::RangeInclusive<usize>| {
+            let mrs: Vec<serde_json::Value> = iids
+                .map(|iid| {
+                    gitlab_mr_json(serde_json::json!({"iid": iid, "target_branch": "release-1.2"}))
+                })
+                .collect();
+            MockResponse::json(&serde_json::Value::from(mrs).to_string())
+        };
+        let path = "/projects/1/merge_requests";
+        server.respond("GET", path, page(1..=GITLAB_PAGE_SIZE));
+        server.respond(
+            "GET",
+            path,
+            page(GITLAB_PAGE_SIZE + 1..=GITLAB_PAGE_SIZE + 2),
+        );
+        let mut remote = make_gitlab_remote(&server);
+        let reqs = remote.find_reqs_by_target_branch("release-1.2").unwrap();
+        assert_eq!(GITLAB_PAGE_SIZE + 2, reqs.len());
+        let queries: Vec<String> = server
+            .requests_to(path)
+            .into_iter()
+            .map(|request| request.query)
+            .collect();
+        assert_eq!(2, queries.len());
+        assert!(queries[0].contains("target_branch=release-1.2"));
+        assert!(queries[1].contains("page=2"));
+    }
 }
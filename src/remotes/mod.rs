@@ -4,18 +4,254 @@ use log::{info, trace};
 use logchop::*;
 use regex::Regex;
 use serde_derive::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use std::io::{stdin, stdout, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
 
 pub mod github;
 pub mod gitlab;
+#[cfg(test)]
+pub mod testing;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct MergeRequest {
     pub id: i64,
+    /// The path of the project the request belongs to, if the remote reports it
+    pub project: String,
     pub title: String,
     pub description: Option<String>,
+    /// Whether the request is open, closed, or merged
+    pub state: String,
     pub source_branch: String,
+    pub target_branch: String,
+    pub author: String,
+    pub labels: Vec<String>,
+    pub reviewers: Vec<String>,
+    pub assignees: Vec<String>,
+    pub milestone: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub draft: bool,
+    pub sha: String,
+    /// The commit that merged the request into its target, or the squashed commit, once merged
+    pub merge_commit_sha: Option<String>,
+    pub web_url: String,
+    pub pipeline_status: PipelineStatus,
+    pub review_status: ReviewStatus,
+    pub approved_by: Vec<String>,
+    pub approved_by_me: bool,
+}
+
+/// The state of the latest CI pipeline or checks run against a request
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PipelineStatus {
+    Passed,
+    Failed,
+    Running,
+    #[default]
+    None,
+}
+
+impl fmt::Display for PipelineStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = match self {
+            PipelineStatus::Passed => "passed",
+            PipelineStatus::Failed => "failed",
+            PipelineStatus::Running => "running",
+            PipelineStatus::None => "none",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+/// The overall review state of a request
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewStatus {
+    Approved,
+    ChangesRequested,
+    ReviewRequired,
+    #[default]
+    Unknown,
+}
+
+impl fmt::Display for ReviewStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = match self {
+            ReviewStatus::Approved => "approved",
+            ReviewStatus::ChangesRequested => "changes requested",
+            ReviewStatus::ReviewRequired => "review required",
+            ReviewStatus::Unknown => "unknown",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+/// A version of a request's commits, as recorded by the remote each time the request is pushed to
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReqRevision {
+    pub base_sha: String,
+    pub head_sha: String,
+    pub created_at: String,
+}
+
+/// A comment left on a request
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct Comment {
+    pub id: i64,
+    pub author: String,
+    pub body: String,
+    pub created_at: String,
+}
+
+/// The line of the request's diff that an inline comment is attached to
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct CommentLocation {
+    pub path: String,
+    /// Absent for comments on a file as a whole, or on lines that are no longer in the diff
+    pub line: Option<i64>,
+    /// The diff hunk the comment was left on, if the remote provides it
+    pub diff_hunk: Option<String>,
+}
+
+/// A thread of comments, either on a request as a whole or on a line of its diff
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct Discussion {
+    pub id: String,
+    pub location: Option<CommentLocation>,
+    /// Absent for threads that can't be resolved
+    pub resolved: Option<bool>,
+    pub comments: Vec<Comment>,
+}
+
+/// Where a line falls within a file's diff, for attaching inline comments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffPosition {
+    pub old_path: String,
+    pub new_path: String,
+    /// Absent for added lines
+    pub old_line: Option<i64>,
+    pub new_line: i64,
+}
+
+/// Whether a request should be closed or reopened
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateChange {
+    Close,
+    Reopen,
+}
+
+/// Changes to make to a request. Fields left as `None` are kept as they are.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReqUpdate {
+    pub state: Option<StateChange>,
+    pub target_branch: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub labels: Option<Vec<String>>,
+    pub reviewers: Option<Vec<String>>,
+    pub assignees: Option<Vec<String>>,
+    /// `Some(None)` removes the milestone
+    pub milestone: Option<Option<String>>,
+    pub draft: Option<bool>,
+}
+
+impl ReqUpdate {
+    /// Determine if the update wouldn't change anything
+    pub fn is_empty(&self) -> bool {
+        *self == ReqUpdate::default()
+    }
+}
+
+/// The merge/pull requests found by a listing or search, which the remote may have cut short
+#[derive(Debug, Default)]
+pub struct FoundReqs {
+    pub reqs: Vec<MergeRequest>,
+    /// How many requests matched, which is more than were returned if they were cut short
+    pub total_count: usize,
+}
+
+impl FoundReqs {
+    /// Requests that are all of those that matched
+    pub fn complete(reqs: Vec<MergeRequest>) -> Self {
+        FoundReqs {
+            total_count: reqs.len(),
+            reqs,
+        }
+    }
+}
+
+/// How a request should be merged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeOptions {
+    pub method: MergeMethod,
+    /// Wait for the pipeline or other requirements before merging
+    pub auto: bool,
+    pub delete_branch: bool,
+    pub message: Option<String>,
+}
+
+/// What became of a request that was asked to be merged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeOutcome {
+    Merged,
+    /// The request will be merged once its requirements are met
+    Scheduled,
+}
+
+/// The details of a request to be opened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewRequest {
+    pub title: String,
+    pub description: String,
+    pub source_branch: String,
+    pub target_branch: String,
+    pub draft: bool,
+}
+
+/// Which requests to include based on their draft status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DraftFilter {
+    All,
+    NoDrafts,
+    DraftsOnly,
+}
+
+/// Fields that requests can be sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Created,
+    Updated,
+    Id,
+    Title,
+}
+
+/// The direction to sort requests in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+/// Fields that requests can be grouped by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    Target,
+    Author,
+    Label,
+}
+
+/// How a request's commits are brought into its target branch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeMethod {
+    Merge,
+    Squash,
+    Rebase,
 }
 
 pub trait Remote {
@@ -25,12 +261,123 @@ pub trait Remote {
     /// Get the local branch associated with the merge request having the given ID
     fn get_local_req_branch(&mut self, mr_id: i64) -> Result<String>;
 
+    /// Get the local branch associated with an already-retrieved merge request
+    fn get_local_req_branch_from(&mut self, mr: &MergeRequest) -> String;
+
     /// Get the remote branch associated with the merge request having the given ID
     fn get_remote_req_branch(&mut self, mr_id: i64) -> Result<String>;
 
+    /// Get the remote ref that tracks the head of the merge request having the given ID, even
+    /// when the request comes from a fork
+    fn get_remote_req_head_ref(&mut self, mr_id: i64) -> String;
+
+    /// Get the merge/pull request having the given ID
+    fn get_req(&mut self, mr_id: i64) -> Result<MergeRequest>;
+
     /// Get the names of the merge/pull requests opened against the remote
     fn get_req_names(&mut self) -> Result<Vec<MergeRequest>>;
 
+    /// Get the open merge/pull requests whose source is the branch with the given name
+    fn find_reqs_by_source_branch(&mut self, branch: &str) -> Result<Vec<MergeRequest>>;
+
+    /// Get every open merge/pull request whose target is the branch with the given name
+    fn find_reqs_by_target_branch(&mut self, branch: &str) -> Result<Vec<MergeRequest>>;
+
+    /// Get the merged merge/pull requests whose source is the branch with the given name
+    fn find_merged_reqs_by_source_branch(&mut self, branch: &str) -> Result<Vec<MergeRequest>>;
+
+    /// Get the merge/pull requests, in any state, that contain the commit with the given SHA
+    fn find_reqs_by_commit(&mut self, sha: &str) -> Result<Vec<MergeRequest>>;
+
+    /// Get the merge/pull requests opened against every project in the remote's group or
+    /// organization. These may lack their branch details; see `fill_req_details`.
+    fn get_group_req_names(&mut self) -> Result<FoundReqs>;
+
+    /// Fill in the branches and head commit of the requests that the predicate picks out, for
+    /// those found through a search that lacked them
+    fn fill_req_details(
+        &mut self,
+        _reqs: &mut [MergeRequest],
+        _needed: &(dyn Fn(&MergeRequest) -> bool + Sync),
+    ) {
+    }
+
+    /// Fill in the latest CI status for each of the given merge/pull requests
+    fn populate_pipeline_statuses(&mut self, reqs: &mut [MergeRequest]);
+
+    /// Fill in the review state and approvers for each of the given merge/pull requests. Remotes
+    /// that can't supply this leave the review status as unknown.
+    fn populate_approvals(&mut self, _reqs: &mut [MergeRequest]) {
+        trace!("Approvals aren't supported for this remote");
+    }
+
+    /// Get the branch that requests target by default
+    fn get_default_branch(&mut self) -> Result<String>;
+
+    /// Open a new merge/pull request
+    fn create_req(&mut self, new_req: &NewRequest) -> Result<MergeRequest>;
+
+    /// Approve the merge/pull request
+    fn approve_req(&mut self, _mr_id: i64) -> Result<()> {
+        Err(anyhow!("Approving requests isn't supported by this forge"))
+    }
+
+    /// Withdraw the current user's approval of the merge/pull request
+    fn unapprove_req(&mut self, _mr_id: i64) -> Result<()> {
+        Err(anyhow!(
+            "Withdrawing approvals isn't supported by this forge"
+        ))
+    }
+
+    /// Review the merge/pull request, asking for changes to be made before it's merged
+    fn request_changes(&mut self, _mr_id: i64, _message: &str) -> Result<()> {
+        Err(anyhow!("Requesting changes isn't supported by this forge"))
+    }
+
+    /// Change the merge/pull request, returning it as updated
+    fn update_req(&mut self, mr_id: i64, update: &ReqUpdate) -> Result<MergeRequest>;
+
+    /// Merge the merge/pull request, provided its head is still the given commit
+    fn merge_req(
+        &mut self,
+        mr_id: i64,
+        head_sha: &str,
+        options: &MergeOptions,
+    ) -> Result<MergeOutcome>;
+
+    /// Get the discussion threads on the merge/pull request, oldest first
+    fn get_discussions(&mut self, mr_id: i64) -> Result<Vec<Discussion>>;
+
+    /// Leave a comment on the merge/pull request as a whole
+    fn create_comment(&mut self, mr_id: i64, body: &str) -> Result<Comment>;
+
+    /// Leave a comment on a line of the merge/pull request's diff, as of the given head commit
+    fn create_inline_comment(
+        &mut self,
+        mr_id: i64,
+        head_sha: &str,
+        position: &DiffPosition,
+        body: &str,
+    ) -> Result<Comment>;
+
+    /// Get the merge/pull request's commits as an mbox of patches, as `git format-patch` writes
+    /// them
+    fn get_req_patches(&mut self, mr_id: i64) -> Result<String>;
+
+    /// Get the revisions the remote has recorded for the merge/pull request, newest first
+    fn get_req_revisions(&mut self, _mr_id: i64) -> Result<Vec<ReqRevision>> {
+        Err(anyhow!("Request revisions aren't supported by this remote"))
+    }
+
+    /// Search the open merge/pull requests by their title and description. The results may lack
+    /// their branch details; see `fill_req_details`.
+    fn search_reqs(&mut self, query: &str) -> Result<FoundReqs> {
+        Ok(FoundReqs::complete(filter_reqs_by_text(
+            self.get_req_names()?,
+            query,
+        )))
+    }
+
     /// Determine if the branch names are useful to display
     fn has_useful_branch_names(&mut self) -> bool;
 
@@ -40,6 +387,9 @@ pub trait Remote {
 
     /// The domain that hosts the remote
     fn get_domain(&mut self) -> &str;
+
+    /// The cache of API responses, which tracks the remote's rate limit
+    fn get_response_cache(&self) -> &ResponseCache;
 }
 
 /// Print a pretty remote
@@ -58,6 +408,206 @@ pub fn get_domain(origin: &str) -> Result<&str> {
     }
 }
 
+/// Read a response's JSON body. Some endpoints reply without a body, which reads as null.
+pub fn read_json_body(response: ureq::Response) -> serde_json::Value {
+    let body = response.into_string().expect("malformed API response");
+    if body.trim().is_empty() {
+        return serde_json::Value::Null;
+    }
+    serde_json::from_str(&body).expect("malformed API response")
+}
+
+/// The API quota remaining, as reported by the most recent response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub remaining: u64,
+    /// When the quota resets, in seconds since the Unix epoch
+    pub reset: u64,
+}
+
+/// A previously-seen API response, along with the tag identifying its version
+#[derive(Debug)]
+struct CachedResponse {
+    etag: String,
+    body: serde_json::Value,
+}
+
+/// Previously-seen API responses, used to make conditional requests so that polling the same
+/// endpoints doesn't eat into the rate limit. Shared between the threads querying the API.
+#[derive(Debug, Default)]
+pub struct ResponseCache {
+    responses: Mutex<HashMap<String, CachedResponse>>,
+    rate_limit: Mutex<Option<RateLimit>>,
+    request_count: AtomicUsize,
+}
+
+impl ResponseCache {
+    /// Send the request, reusing the cached body if the server reports it as unchanged
+    pub fn query(
+        &self,
+        request: &mut ureq::Request,
+        url: &str,
+    ) -> Result<serde_json::Value, Box<ureq::Response>> {
+        if let Some(cached) = self.responses.lock().unwrap().get(url) {
+            request.set("If-None-Match", &cached.etag);
+        }
+        let response = request.call();
+        self.request_count.fetch_add(1, Ordering::SeqCst);
+        self.record_rate_limit(&response);
+        if response.status() == 304 {
+            trace!("Cached response for {} is current", url);
+            if let Some(cached) = self.responses.lock().unwrap().get(url) {
+                return Ok(cached.body.clone());
+            }
+        }
+        if response.error() || response.status() == 304 {
+            return Err(Box::new(response));
+        }
+        let etag = response.header("ETag").map(String::from);
+        let body = response.into_json().expect("malformed API response");
+        if let Some(etag) = etag {
+            let cached = CachedResponse {
+                etag,
+                body: body.clone(),
+            };
+            self.responses
+                .lock()
+                .unwrap()
+                .insert(String::from(url), cached);
+        }
+        Ok(body)
+    }
+
+    /// The rate limit reported by the most recent response, if the server reports one
+    pub fn get_rate_limit(&self) -> Option<RateLimit> {
+        *self.rate_limit.lock().unwrap()
+    }
+
+    /// Get the number of requests sent since this was last called
+    pub fn take_request_count(&self) -> usize {
+        self.request_count.swap(0, Ordering::SeqCst)
+    }
+
+    /// Note the rate limit headers sent by GitHub (`X-RateLimit-*`) or GitLab (`RateLimit-*`)
+    fn record_rate_limit(&self, response: &ureq::Response) {
+        let header = |name: &str| {
+            response
+                .header(&format!("X-{}", name))
+                .or_else(|| response.header(name))
+                .and_then(|value| value.trim().parse::<u64>().ok())
+        };
+        if let (Some(remaining), Some(reset)) =
+            (header("RateLimit-Remaining"), header("RateLimit-Reset"))
+        {
+            *self.rate_limit.lock().unwrap() = Some(RateLimit { remaining, reset });
+        }
+    }
+}
+
+/// The maximum number of API queries to have in flight at once
+const MAX_CONCURRENT_QUERIES: usize = 8;
+
+/// Run the function against every request, spreading the work across a handful of threads
+pub fn for_each_req_concurrently<F>(reqs: &mut [MergeRequest], func: F)
+where
+    F: Fn(&mut MergeRequest) + Sync,
+{
+    if reqs.is_empty() {
+        return;
+    }
+    let chunk_size = reqs.len().div_ceil(MAX_CONCURRENT_QUERIES);
+    let func = &func;
+    thread::scope(|scope| {
+        for chunk in reqs.chunks_mut(chunk_size) {
+            scope.spawn(move || chunk.iter_mut().for_each(func));
+        }
+    });
+}
+
+/// Filter the requests down to those whose title or description contain every term in the query
+pub fn filter_reqs_by_text(reqs: Vec<MergeRequest>, query: &str) -> Vec<MergeRequest> {
+    let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    reqs.into_iter()
+        .filter(|req| {
+            let haystack = format!(
+                "{}\n{}",
+                req.title,
+                req.description.as_deref().unwrap_or_default()
+            )
+            .to_lowercase();
+            terms.iter().all(|term| haystack.contains(term.as_str()))
+        })
+        .collect()
+}
+
+/// Filter the requests down to those matching the draft filter
+pub fn filter_reqs_by_draft(reqs: Vec<MergeRequest>, filter: DraftFilter) -> Vec<MergeRequest> {
+    reqs.into_iter()
+        .filter(|req| match filter {
+            DraftFilter::All => true,
+            DraftFilter::NoDrafts => !req.draft,
+            DraftFilter::DraftsOnly => req.draft,
+        })
+        .collect()
+}
+
+/// Sort the requests by the given field. Timestamps are compared as ISO 8601 strings.
+pub fn sort_reqs<T: Borrow<MergeRequest>>(reqs: &mut [T], key: SortKey, order: SortOrder) {
+    reqs.sort_by(|a, b| {
+        let (a, b) = (a.borrow(), b.borrow());
+        let ordering = match key {
+            SortKey::Created => a.created_at.cmp(&b.created_at),
+            SortKey::Updated => a.updated_at.cmp(&b.updated_at),
+            SortKey::Id => a.id.cmp(&b.id),
+            SortKey::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+        };
+        match order {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        }
+    });
+}
+
+/// Group the requests into named sections, ordered by name. Requests with several labels appear
+/// in each of their labels' sections.
+pub fn group_reqs<T: Borrow<MergeRequest>>(
+    reqs: &[T],
+    group_by: GroupBy,
+) -> Vec<(String, Vec<&T>)> {
+    let mut groups: BTreeMap<String, Vec<&T>> = BTreeMap::new();
+    for item in reqs {
+        let req = item.borrow();
+        let names = match group_by {
+            GroupBy::Target => vec![req.target_branch.clone()],
+            GroupBy::Author => vec![req.author.clone()],
+            GroupBy::Label if req.labels.is_empty() => vec![String::from("(no label)")],
+            GroupBy::Label => req.labels.clone(),
+        };
+        for name in names {
+            groups.entry(name).or_default().push(item);
+        }
+    }
+    groups.into_iter().collect()
+}
+
+/// Percent-encode a value for use in a URL query string
+pub fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                String::from(byte as char)
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Determine if an API key has been stored for the origin's domain, without asking for one
+pub fn has_stored_api_key(origin: &str) -> bool {
+    get_domain(origin).is_ok_and(|domain| git::get_req_config(domain, "apikey").is_some())
+}
+
 /// Get the API key for the given domain. If absent, prompt.
 fn get_api_key(domain: &str) -> String {
     git::get_req_config(domain, "apikey").unwrap_or_else(|| {
@@ -89,6 +639,7 @@ pub fn get_remote(remote_name: &str, origin: &str, skip_api_key: bool) -> Result
                 origin: String::from(origin),
                 api_root: String::from("https://api.github.com/repos"),
                 api_key: String::from(""),
+                cache: ResponseCache::default(),
             };
             if !skip_api_key {
                 let apikey = get_api_key("github.com");
@@ -118,6 +669,7 @@ pub fn get_remote(remote_name: &str, origin: &str, skip_api_key: bool) -> Result
                 origin: String::from(origin),
                 api_root: format!("https://{}/api/v4", gitlab_domain),
                 api_key: String::from(""),
+                cache: ResponseCache::default(),
             };
             if !skip_api_key {
                 let apikey = get_api_key(domain);
@@ -144,3 +696,170 @@ pub fn get_remote(remote_name: &str, origin: &str, skip_api_key: bool) -> Result
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_req(id: i64, title: &str, description: Option<&str>) -> MergeRequest {
+        MergeRequest {
+            id,
+            project: String::new(),
+            title: String::from(title),
+            description: description.map(String::from),
+            state: String::from("open"),
+            source_branch: format!("branch-{}", id),
+            target_branch: String::from("main"),
+            author: String::from("aru"),
+            labels: vec![],
+            reviewers: vec![],
+            assignees: vec![],
+            milestone: None,
+            created_at: format!("2020-01-{:02}T00:00:00Z", 30 - id),
+            updated_at: format!("2020-02-{:02}T00:00:00Z", id),
+            draft: false,
+            sha: String::new(),
+            merge_commit_sha: None,
+            web_url: String::new(),
+            pipeline_status: PipelineStatus::None,
+            review_status: ReviewStatus::Unknown,
+            approved_by: vec![],
+            approved_by_me: false,
+        }
+    }
+
+    fn make_draft_req(id: i64, title: &str) -> MergeRequest {
+        MergeRequest {
+            draft: true,
+            ..make_req(id, title, None)
+        }
+    }
+
+    fn ids(reqs: &[MergeRequest]) -> Vec<i64> {
+        reqs.iter().map(|req| req.id).collect()
+    }
+
+    #[test]
+    fn test_filter_reqs_by_text_title() {
+        let reqs = vec![
+            make_req(1, "Fix login timeout", None),
+            make_req(2, "Add dark mode", None),
+        ];
+        let found = filter_reqs_by_text(reqs, "login");
+        assert_eq!(vec![1], ids(&found));
+    }
+
+    #[test]
+    fn test_filter_reqs_by_text_description() {
+        let reqs = vec![
+            make_req(
+                1,
+                "Fix session handling",
+                Some("Resolves the LOGIN timeout"),
+            ),
+            make_req(2, "Add dark mode", Some("Because login screens are bright")),
+        ];
+        let found = filter_reqs_by_text(reqs, "login timeout");
+        assert_eq!(vec![1], ids(&found));
+    }
+
+    #[test]
+    fn test_filter_reqs_by_draft_no_drafts() {
+        let reqs = vec![make_req(1, "Ready", None), make_draft_req(2, "WIP")];
+        let found = filter_reqs_by_draft(reqs, DraftFilter::NoDrafts);
+        assert_eq!(vec![1], ids(&found));
+    }
+
+    #[test]
+    fn test_filter_reqs_by_draft_drafts_only() {
+        let reqs = vec![make_req(1, "Ready", None), make_draft_req(2, "WIP")];
+        let found = filter_reqs_by_draft(reqs, DraftFilter::DraftsOnly);
+        assert_eq!(vec![2], ids(&found));
+    }
+
+    #[test]
+    fn test_sort_reqs_created() {
+        let mut reqs = vec![
+            make_req(1, "b", None),
+            make_req(3, "c", None),
+            make_req(2, "a", None),
+        ];
+        sort_reqs(&mut reqs, SortKey::Created, SortOrder::Asc);
+        assert_eq!(vec![3, 2, 1], ids(&reqs));
+    }
+
+    #[test]
+    fn test_sort_reqs_updated_desc() {
+        let mut reqs = vec![
+            make_req(1, "b", None),
+            make_req(3, "c", None),
+            make_req(2, "a", None),
+        ];
+        sort_reqs(&mut reqs, SortKey::Updated, SortOrder::Desc);
+        assert_eq!(vec![3, 2, 1], ids(&reqs));
+    }
+
+    #[test]
+    fn test_sort_reqs_title() {
+        let mut reqs = vec![
+            make_req(1, "b", None),
+            make_req(3, "C", None),
+            make_req(2, "a", None),
+        ];
+        sort_reqs(&mut reqs, SortKey::Title, SortOrder::Asc);
+        assert_eq!(vec![2, 1, 3], ids(&reqs));
+    }
+
+    #[test]
+    fn test_group_reqs_target() {
+        let mut release = make_req(2, "Backport", None);
+        release.target_branch = String::from("release-1.2");
+        let reqs = vec![
+            make_req(1, "Feature", None),
+            release,
+            make_req(3, "Fix", None),
+        ];
+        let groups = group_reqs(&reqs, GroupBy::Target);
+        let summary: Vec<(&str, Vec<i64>)> = groups
+            .iter()
+            .map(|(name, reqs)| (name.as_str(), reqs.iter().map(|req| req.id).collect()))
+            .collect();
+        assert_eq!(
+            vec![("main", vec![1, 3]), ("release-1.2", vec![2])],
+            summary
+        );
+    }
+
+    #[test]
+    fn test_group_reqs_label() {
+        let mut labelled = make_req(1, "Feature", None);
+        labelled.labels = vec![String::from("ui"), String::from("bug")];
+        let reqs = vec![labelled, make_req(2, "Fix", None)];
+        let groups = group_reqs(&reqs, GroupBy::Label);
+        let summary: Vec<(&str, Vec<i64>)> = groups
+            .iter()
+            .map(|(name, reqs)| (name.as_str(), reqs.iter().map(|req| req.id).collect()))
+            .collect();
+        assert_eq!(
+            vec![("(no label)", vec![2]), ("bug", vec![1]), ("ui", vec![1])],
+            summary
+        );
+    }
+
+    #[test]
+    fn test_for_each_req_concurrently() {
+        let mut reqs: Vec<MergeRequest> = (1..=20).map(|id| make_req(id, "MR", None)).collect();
+        for_each_req_concurrently(&mut reqs, |req| req.sha = format!("sha-{}", req.id));
+        for req in reqs {
+            assert_eq!(format!("sha-{}", req.id), req.sha);
+        }
+    }
+
+    #[test]
+    fn test_encode_query_value() {
+        assert_eq!(
+            "is%3Apr%20repo%3Amy_org%2Fmy_project",
+            encode_query_value("is:pr repo:my_org/my_project")
+        );
+    }
+}
@@ -0,0 +1,226 @@
+//! A stand-in for the GitHub and GitLab APIs, for exercising the remotes without the network
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::remotes::gitlab::GitLab;
+use crate::remotes::ResponseCache;
+
+/// A canned response to send back for a request
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl MockResponse {
+    /// A successful JSON response
+    pub fn json(body: &str) -> Self {
+        MockResponse {
+            status: 200,
+            headers: vec![(
+                String::from("Content-Type"),
+                String::from("application/json"),
+            )],
+            body: String::from(body),
+        }
+    }
+
+    /// A successful plain text response
+    pub fn text(body: &str) -> Self {
+        MockResponse {
+            status: 200,
+            headers: vec![(String::from("Content-Type"), String::from("text/plain"))],
+            body: String::from(body),
+        }
+    }
+
+    /// A bodiless response with the given status
+    pub fn status(status: u16) -> Self {
+        MockResponse {
+            status,
+            headers: vec![],
+            body: String::new(),
+        }
+    }
+
+    /// Add a header to the response
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((String::from(name), String::from(value)));
+        self
+    }
+}
+
+/// A request received by the mock server
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub query: String,
+    pub headers: HashMap<String, String>,
+}
+
+impl RecordedRequest {
+    /// Get the value of a header, matching its name case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_lowercase()).map(String::as_str)
+    }
+}
+
+type Routes = HashMap<(String, String), Vec<MockResponse>>;
+
+/// An HTTP server that replays canned responses for each method and path (sans query string).
+/// Responses for a route are handed out in order, with the last one repeating indefinitely.
+pub struct MockServer {
+    pub url: String,
+    routes: Arc<Mutex<Routes>>,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl MockServer {
+    /// Start serving on a random local port
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("couldn't bind the mock server");
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let routes: Arc<Mutex<Routes>> = Arc::new(Mutex::new(HashMap::new()));
+        let requests = Arc::new(Mutex::new(vec![]));
+        let (thread_routes, thread_requests) = (Arc::clone(&routes), Arc::clone(&requests));
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let (routes, requests) = (Arc::clone(&thread_routes), Arc::clone(&thread_requests));
+                thread::spawn(move || handle_connection(stream, &routes, &requests));
+            }
+        });
+        MockServer {
+            url,
+            routes,
+            requests,
+        }
+    }
+
+    /// Queue up a response for the method and path
+    pub fn respond(&self, method: &str, path: &str, response: MockResponse) {
+        self.routes
+            .lock()
+            .unwrap()
+            .entry((String::from(method), String::from(path)))
+            .or_default()
+            .push(response);
+    }
+
+    /// Get the requests made to the path so far, oldest first
+    pub fn requests_to(&self, path: &str) -> Vec<RecordedRequest> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|request| request.path == path)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Read a request off the stream and reply with the next response queued for its route
+fn handle_connection(
+    mut stream: TcpStream,
+    routes: &Mutex<Routes>,
+    requests: &Mutex<Vec<RecordedRequest>>,
+) {
+    let request = match read_request(&mut stream) {
+        Some(request) => request,
+        None => return,
+    };
+    let response = {
+        let mut routes = routes.lock().unwrap();
+        match routes.get_mut(&(request.method.clone(), request.path.clone())) {
+            Some(queue) if queue.len() > 1 => queue.remove(0),
+            Some(queue) if !queue.is_empty() => queue[0].clone(),
+            _ => MockResponse::status(404),
+        }
+    };
+    requests.lock().unwrap().push(request);
+    let mut raw = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        raw.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    raw.push_str("\r\n");
+    raw.push_str(&response.body);
+    let _ = stream.write_all(raw.as_bytes());
+}
+
+/// Parse an HTTP/1.1 request, consuming any body sized by its `Content-Length`
+fn read_request(stream: &mut TcpStream) -> Option<RecordedRequest> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).ok()?;
+    let mut parts = request_line.split_whitespace();
+    let method = String::from(parts.next()?);
+    let target = parts.next()?;
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let (path, query) = (String::from(path), String::from(query));
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), String::from(value.trim()));
+        }
+    }
+    let length = headers
+        .get("content-length")
+        .and_then(|length| length.parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+    Some(RecordedRequest {
+        method,
+        path,
+        query,
+        headers,
+    })
+}
+
+/// A GitLab remote for `my_namespace/my_project` that talks to the mock server
+pub fn make_gitlab_remote(server: &MockServer) -> GitLab {
+    GitLab {
+        id: String::from("1"),
+        domain: String::from("gitlab.example.com"),
+        name: String::from("my_project"),
+        namespace: String::from("my_namespace"),
+        full_path: String::from("my_namespace/my_project"),
+        origin: String::from("git@gitlab.example.com:my_namespace/my_project.git"),
+        api_root: server.url.clone(),
+        api_key: String::from("token"),
+        cache: ResponseCache::default(),
+    }
+}
+
+/// A merge request of the GitLab remote's project as the API returns it, with the given fields
+/// overridden
+pub fn gitlab_mr_json(overrides: serde_json::Value) -> serde_json::Value {
+    let mut mr = serde_json::json!({
+        "id": 50, "iid": 5, "title": "Add merge", "description": null,
+        "state": "opened", "target_branch": "main", "source_branch": "add-merge",
+        "author": {"id": 1, "username": "aru"}, "labels": [],
+        "created_at": "2020-01-01T00:00:00.000Z",
+        "updated_at": "2020-01-02T00:00:00.000Z", "sha": "abc",
+        "web_url": "https://gitlab.example.com/my_namespace/my_project/-/merge_requests/5"
+    });
+    if let serde_json::Value::Object(overrides) = overrides {
+        for (field, value) in overrides {
+            mr[field] = value;
+        }
+    }
+    mr
+}
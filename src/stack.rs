@@ -0,0 +1,618 @@
+//! Work with stacks of requests, where each request targets the source branch of the one below it
+use crate::remotes::{MergeRequest, Remote, ReqUpdate};
+use crate::{abort, checkout_mr, get_remote_hard, get_request_id_or_current, git};
+use anyhow::Result;
+use colored::*;
+use log::{debug, info, trace};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+use tabwriter::TabWriter;
+
+/// What to do with the stack besides showing it
+#[derive(Debug)]
+pub struct StackOptions {
+    /// Check out a local branch for every open request in the stack
+    pub checkout: bool,
+    /// Rebase each request onto the latest version of the one it's stacked on
+    pub rebase: bool,
+    /// Push the rebased branches, then retarget the requests whose base was merged
+    pub push: bool,
+}
+
+/// A request within a stack
+#[derive(Debug)]
+struct StackEntry<'a> {
+    /// How many requests it's stacked on
+    depth: usize,
+    mr: &'a MergeRequest,
+    /// The request it's stacked on, unless it's at the bottom of the stack
+    base: Option<&'a MergeRequest>,
+}
+
+/// Find the request that the given one is stacked on, which is the one whose source branch it
+/// targets. Branches can be reused after their request is merged, so open requests win.
+fn find_base<'a>(reqs: &'a [MergeRequest], mr: &MergeRequest) -> Option<&'a MergeRequest> {
+    reqs.iter()
+        .filter(|req| req.id != mr.id && req.source_branch == mr.target_branch)
+        .min_by_key(|req| req.state != "open")
+}
+
+/// Add the request to the stack, followed by the open requests stacked on top of it
+fn push_with_descendants<'a>(
+    reqs: &'a [MergeRequest],
+    entry: StackEntry<'a>,
+    stack: &mut Vec<StackEntry<'a>>,
+    seen: &mut HashSet<i64>,
+) {
+    let (mr, depth) = (entry.mr, entry.depth);
+    if !seen.insert(mr.id) {
+        return;
+    }
+    stack.push(entry);
+    for req in reqs.iter().filter(|req| {
+        req.state == "open" && find_base(reqs, req).map(|base| base.id) == Some(mr.id)
+    }) {
+        let entry = StackEntry {
+            depth: depth + 1,
+            mr: req,
+            base: Some(mr),
+        };
+        push_with_descendants(reqs, entry, stack, seen);
+    }
+}
+
+/// Arrange the stack containing the request. It starts from the bottom-most request the given one
+/// is stacked on, and each request is followed by those stacked on top of it.
+fn build_stack(reqs: &[MergeRequest], mr_id: i64) -> Vec<StackEntry<'_>> {
+    let Some(mut bottom) = reqs.iter().find(|req| req.id == mr_id) else {
+        return vec![];
+    };
+    let mut seen = HashSet::from([bottom.id]);
+    while let Some(base) = find_base(reqs, bottom) {
+        if !seen.insert(base.id) {
+            trace!("Requests {} and {} target each other", bottom.id, base.id);
+            break;
+        }
+        bottom = base;
+    }
+    let mut stack = vec![];
+    let entry = StackEntry {
+        depth: 0,
+        mr: bottom,
+        base: None,
+    };
+    push_with_descendants(reqs, entry, &mut stack, &mut HashSet::new());
+    stack
+}
+
+/// Get the open requests, along with the given request and any merged requests at the bottom of
+/// its stack, which the rest of the stack may still target
+fn fetch_stack_reqs(remote: &mut dyn Remote, mr_id: i64) -> Result<Vec<MergeRequest>> {
+    let mut reqs = remote.get_req_names()?;
+    if !reqs.iter().any(|req| req.id == mr_id) {
+        reqs.push(remote.get_req(mr_id)?);
+    }
+    let default_branch = remote.get_default_branch()?;
+    loop {
+        let target = match build_stack(&reqs, mr_id).first() {
+            Some(bottom) if bottom.mr.target_branch != default_branch => {
+                bottom.mr.target_branch.clone()
+            }
+            _ => break,
+        };
+        let merged = remote.find_merged_reqs_by_source_branch(&target)?;
+        match merged
+            .into_iter()
+            .find(|req| reqs.iter().all(|known| known.id != req.id))
+        {
+            Some(req) => {
+                debug!("Found merged request {} beneath the stack", req.id);
+                reqs.push(req);
+            }
+            None => break,
+        }
+    }
+    Ok(reqs)
+}
+
+/// Print the stack as a tree, marking the given request
+fn print_stack(stack: &[StackEntry], mr_id: i64) {
+    if let Some(bottom) = stack.first() {
+        println!("Stacked on {}", bottom.mr.target_branch.green().dimmed());
+    }
+    let mut tw = TabWriter::new(io::stdout()).padding(2);
+    for entry in stack {
+        let mr = entry.mr;
+        let marker = if mr.id == mr_id { "*" } else { " " };
+        let state = match mr.state.as_str() {
+            "open" if mr.draft => "draft".yellow(),
+            "open" => "open".green(),
+            "merged" => "merged".magenta(),
+            state => state.red(),
+        };
+        writeln!(
+            &mut tw,
+            "{} {}{}\t{}\t{}\t{}",
+            marker,
+            "  ".repeat(entry.depth),
+            mr.id.to_string().green(),
+            state,
+            mr.source_branch.green().dimmed(),
+            mr.title
+        )
+        .unwrap();
+    }
+    tw.flush().unwrap();
+}
+
+/// Check out a branch for every open request in the stack, finishing on the given request
+fn checkout_stack(remote_name: &str, remote: &mut dyn Remote, stack: &[StackEntry], mr_id: i64) {
+    for entry in stack.iter().filter(|entry| entry.mr.state == "open") {
+        if entry.mr.id == mr_id {
+            continue;
+        }
+        let remote_branch_name =
+            remote
+                .get_remote_req_branch(entry.mr.id)
+                .unwrap_or_else(|error| {
+                    let message = format!(
+                        "There was a problem ascertaining the branch name: {}",
+                        &error
+                    );
+                    abort(&message);
+                });
+        git::checkout_branch(
+            remote_name,
+            &remote_branch_name,
+            &remote.get_local_req_branch_from(entry.mr),
+            remote.has_virtual_remote_branch_names(),
+        )
+        .unwrap_or_else(|error| {
+            let message = format!("There was an error checking out the branch: {}", error);
+            abort(&message);
+        });
+    }
+    if stack
+        .iter()
+        .any(|entry| entry.mr.id == mr_id && entry.mr.state == "open")
+    {
+        checkout_mr(remote_name, mr_id);
+    }
+}
+
+/// The project config field marking a rebase of the stack that was interrupted
+const REBASE_STATE_FIELD: &str = "stack.rebasing";
+
+/// The hidden ref that holds a request's head while rebasing the stack
+fn get_head_ref_name(mr_id: i64) -> String {
+    git::get_hidden_ref_name(&format!("stack/{}/head", mr_id))
+}
+
+/// The hidden ref that holds a branch's tip from before the stack was rebased, so that an
+/// interrupted rebase can be resumed
+fn get_rebased_from_ref_name(mr_id: i64) -> String {
+    git::get_hidden_ref_name(&format!("stack/{}/rebased-from", mr_id))
+}
+
+/// Determine if a local branch holds every commit pushed to its request. A branch that an
+/// interrupted rebase of the stack already rebased only needs to have held them beforehand.
+fn has_pushed_commits(
+    head: &str,
+    local_tip: &str,
+    rebased_from: Option<&str>,
+    is_ancestor: impl Fn(&str, &str) -> Result<bool>,
+) -> Result<bool> {
+    if is_ancestor(head, local_tip)? {
+        return Ok(true);
+    }
+    match rebased_from {
+        Some(rebased_from) => is_ancestor(head, rebased_from),
+        None => Ok(false),
+    }
+}
+
+/// Determine if a branch still needs rebasing. One already rebased by an interrupted rebase of the
+/// stack is done once it's on top of its new base.
+fn needs_rebase(
+    new_base: &str,
+    local_tip: &str,
+    rebased_from: Option<&str>,
+    is_ancestor: impl Fn(&str, &str) -> Result<bool>,
+) -> Result<bool> {
+    match rebased_from {
+        Some(_) => Ok(!is_ancestor(new_base, local_tip)?),
+        None => Ok(true),
+    }
+}
+
+/// Delete the refs fetched or saved while rebasing the stack
+fn delete_stack_refs(stack: &[StackEntry]) {
+    for entry in stack {
+        if let Err(error) = git::delete_hidden_refs(&format!("stack/{}", entry.mr.id)) {
+            debug!("Couldn't clean up the fetched refs: {}", error);
+        }
+    }
+}
+
+/// Fetch the head of every request in the stack, along with the branches that the requests on
+/// top of them will be rebased onto
+fn fetch_stack(remote_name: &str, remote: &mut dyn Remote, stack: &[StackEntry]) -> Result<()> {
+    let mut refspecs = vec![];
+    for entry in stack {
+        refspecs.push((
+            remote.get_remote_req_head_ref(entry.mr.id),
+            get_head_ref_name(entry.mr.id),
+        ));
+        if !stack
+            .iter()
+            .any(|other| other.base.map(|base| base.id) == Some(entry.mr.id))
+        {
+            continue;
+        }
+        // Keep the remote-tracking branches current so that their reflogs show how they moved
+        let branch = match entry.mr.state.as_str() {
+            "merged" => &entry.mr.target_branch,
+            _ => &entry.mr.source_branch,
+        };
+        refspecs.push((
+            format!("refs/heads/{}", branch),
+            format!("refs/remotes/{}/{}", remote_name, branch),
+        ));
+    }
+    let refspecs: Vec<(&str, &str)> = refspecs
+        .iter()
+        .map(|(remote_ref, local_ref)| (remote_ref.as_str(), local_ref.as_str()))
+        .collect();
+    git::fetch_refs(remote_name, &refspecs)
+}
+
+/// Rebase each open request in the stack onto the latest version of the request it's stacked on,
+/// or onto that request's target once it's merged. A branch's old base is where it forked from
+/// its base's branch going by the reflogs, so that amended or rebased bases are handled. Each
+/// branch's tip is saved before it's rebased, so that a rebase stopped by conflicts can be
+/// resumed by running it again.
+fn rebase_stack(
+    remote_name: &str,
+    remote: &mut dyn Remote,
+    stack: &[StackEntry],
+    mr_id: i64,
+    push: bool,
+) {
+    if git::get_project_config(REBASE_STATE_FIELD).is_some() {
+        println!("Resuming the interrupted rebase of the stack");
+    } else {
+        // Tips saved by an abandoned rebase would be mistaken for this one's
+        delete_stack_refs(stack);
+        git::set_project_config(REBASE_STATE_FIELD, &mr_id.to_string());
+    }
+    let rebased_from: HashMap<i64, String> = stack
+        .iter()
+        .filter_map(|entry| {
+            let tip = git::resolve_commit(&get_rebased_from_ref_name(entry.mr.id)).ok()?;
+            Some((entry.mr.id, tip))
+        })
+        .collect();
+    fetch_stack(remote_name, remote, stack).unwrap_or_else(|error| {
+        let message = format!("There was a problem fetching the stack: {}", error);
+        abort(&message);
+    });
+    let open: Vec<&StackEntry> = stack
+        .iter()
+        .filter(|entry| entry.mr.state == "open")
+        .collect();
+    // Check every branch before rebasing any, so that a stale one doesn't leave the stack half
+    // rebased
+    let mut local_branches = HashMap::new();
+    for entry in &open {
+        let local_branch_name =
+            git::get_local_branch_name(remote_name, &remote.get_local_req_branch_from(entry.mr));
+        let head_ref = get_head_ref_name(entry.mr.id);
+        let is_current = git::ensure_branch(&local_branch_name, &head_ref)
+            .and_then(|_| {
+                has_pushed_commits(
+                    &head_ref,
+                    &local_branch_name,
+                    rebased_from.get(&entry.mr.id).map(String::as_str),
+                    git::is_ancestor,
+                )
+            })
+            .unwrap_or_else(|error| {
+                let message = format!("There was a problem reading the branches: {}", error);
+                abort(&message);
+            });
+        if !is_current {
+            let message = format!(
+                "'{}' is missing commits pushed to request {}; update it first",
+                local_branch_name, entry.mr.id
+            );
+            abort(&message);
+        }
+        local_branches.insert(entry.mr.id, local_branch_name);
+    }
+    let original_branch = git::get_current_branch_name();
+    // The branches rebased before an interruption are based on their saved tips
+    let mut old_tips = rebased_from.clone();
+    for entry in &open {
+        let Some(base) = entry.base else {
+            continue;
+        };
+        let local_branch_name = &local_branches[&entry.mr.id];
+        let (new_base, old_base) = match base.state.as_str() {
+            "merged" => (
+                format!("{}/{}", remote_name, base.target_branch),
+                Ok(get_head_ref_name(base.id)),
+            ),
+            "open" => {
+                let base_branch_name = &local_branches[&base.id];
+                let old_base = match old_tips.get(&base.id) {
+                    Some(tip) => Ok(tip.clone()),
+                    None => {
+                        let tracking_branch = format!("{}/{}", remote_name, base.source_branch);
+                        git::find_fork_point(
+                            &[base_branch_name, &tracking_branch],
+                            local_branch_name,
+                        )
+                    }
+                };
+                (base_branch_name.clone(), old_base)
+            }
+            _ => {
+                debug!("Leaving {} on closed request {}", entry.mr.id, base.id);
+                continue;
+            }
+        };
+        let saved_tip = rebased_from.get(&entry.mr.id).map(String::as_str);
+        let tip = git::resolve_commit(local_branch_name);
+        let result = needs_rebase(&new_base, local_branch_name, saved_tip, git::is_ancestor)
+            .and_then(|needed| {
+                if !needed {
+                    trace!("{} was already rebased", local_branch_name);
+                    return Ok(());
+                }
+                let old_base = old_base?;
+                if saved_tip.is_none() {
+                    git::update_ref(&get_rebased_from_ref_name(entry.mr.id), local_branch_name)?;
+                }
+                git::rebase_branch(local_branch_name, &new_base, &old_base)
+            });
+        if let Err(error) = result {
+            let message = format!(
+                "{}. If it stopped for conflicts, resolve them and run 'git rebase --continue', \
+                 then run 'git req stack {} --rebase' again to carry on with the rest of the \
+                 stack.",
+                error, mr_id
+            );
+            abort(&message);
+        }
+        let tip = tip.unwrap_or_default();
+        if git::resolve_commit(local_branch_name).ok().as_ref() != Some(&tip) {
+            println!(
+                "Rebased {} onto {}",
+                local_branch_name.green(),
+                new_base.green()
+            );
+            old_tips.entry(entry.mr.id).or_insert(tip);
+        }
+    }
+    if let Some(branch) = original_branch {
+        if let Err(error) = git::switch_branch(&branch) {
+            debug!("Couldn't return to {}: {}", branch, error);
+        }
+    }
+    delete_stack_refs(stack);
+    if let Err(error) = git::delete_project_config(REBASE_STATE_FIELD) {
+        trace!("Couldn't delete {}: {}", REBASE_STATE_FIELD, error);
+    }
+    if !push {
+        println!("Push the rebased branches, then retarget any requests whose base was merged");
+        return;
+    }
+    push_stack(remote_name, remote, &open, &local_branches);
+}
+
+/// Push the branches that were rebased, then retarget the requests whose base was merged onto
+/// that base's target
+fn push_stack(
+    remote_name: &str,
+    remote: &mut dyn Remote,
+    open: &[&StackEntry],
+    local_branches: &HashMap<i64, String>,
+) {
+    for entry in open {
+        let local_branch_name = &local_branches[&entry.mr.id];
+        if git::resolve_commit(local_branch_name).ok().as_ref() == Some(&entry.mr.sha) {
+            continue;
+        }
+        git::force_push_branch(
+            remote_name,
+            local_branch_name,
+            &entry.mr.source_branch,
+            &entry.mr.sha,
+        )
+        .unwrap_or_else(|error| {
+            let message = format!("There was a problem pushing the branch: {}", error);
+            abort(&message);
+        });
+    }
+    for entry in open {
+        let Some(base) = entry.base.filter(|base| base.state == "merged") else {
+            continue;
+        };
+        let update = ReqUpdate {
+            target_branch: Some(base.target_branch.clone()),
+            ..ReqUpdate::default()
+        };
+        remote
+            .update_req(entry.mr.id, &update)
+            .unwrap_or_else(|error| {
+                let message = format!("There was a problem retargeting {}: {}", entry.mr.id, error);
+                abort(&message);
+            });
+        println!(
+            "Retargeted request {} to {}",
+            entry.mr.id.to_string().green(),
+            base.target_branch
+        );
+    }
+}
+
+/// Show the stack of requests that the given request, or the one for the current branch, belongs
+/// to, then check out or rebase it if asked to
+pub fn stack_requests(remote_name: &str, request_id: Option<String>, options: &StackOptions) {
+    let mut remote = get_remote_hard(remote_name, true);
+    debug!("Found remote: {}", remote);
+    let mr_id = get_request_id_or_current(remote_name, remote.as_mut(), request_id);
+    info!("Finding the stack for MR {}", mr_id);
+    let reqs = fetch_stack_reqs(remote.as_mut(), mr_id).unwrap_or_else(|error| {
+        let message = format!("There was a problem querying the requests: {}", error);
+        abort(&message);
+    });
+    let stack = build_stack(&reqs, mr_id);
+    if stack.len() < 2 {
+        println!(
+            "Request {} isn't stacked with any others",
+            mr_id.to_string().green()
+        );
+        return;
+    }
+    print_stack(&stack, mr_id);
+    if options.checkout {
+        checkout_stack(remote_name, remote.as_mut(), &stack, mr_id);
+    } else if options.rebase {
+        rebase_stack(remote_name, remote.as_mut(), &stack, mr_id, options.push);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_req(id: i64, source_branch: &str, target_branch: &str, state: &str) -> MergeRequest {
+        MergeRequest {
+            id,
+            title: format!("Request {}", id),
+            state: String::from(state),
+            source_branch: String::from(source_branch),
+            target_branch: String::from(target_branch),
+            ..MergeRequest::default()
+        }
+    }
+
+    /// Summarize the stack as (ID, depth, base ID) tuples
+    fn summarize(stack: &[StackEntry]) -> Vec<(i64, usize, Option<i64>)> {
+        stack
+            .iter()
+            .map(|entry| (entry.mr.id, entry.depth, entry.base.map(|base| base.id)))
+            .collect()
+    }
+
+    /// Answer ancestry queries from a graph of commit to parent
+    fn make_is_ancestor(
+        parents: &'static [(&'static str, &'static str)],
+    ) -> impl Fn(&str, &str) -> Result<bool> {
+        move |ancestor, descendant| {
+            let mut commit = Some(descendant);
+            while let Some(current) = commit {
+                if current == ancestor {
+                    return Ok(true);
+                }
+                commit = parents
+                    .iter()
+                    .find(|(child, _)| *child == current)
+                    .map(|(_, parent)| *parent);
+            }
+            Ok(false)
+        }
+    }
+
+    #[test]
+    fn test_rebase_stack_resume() {
+        // part-1 was pushed as a1 and amended to a1', part-2 (b1) was rebased onto it as b1' by
+        // the interrupted run, and part-3 (c1) stopped for conflicts before being rebased
+        let is_ancestor = make_is_ancestor(&[
+            ("a1", "main"),
+            ("a1'", "main"),
+            ("b1", "a1"),
+            ("b1'", "a1'"),
+            ("c1", "b1"),
+        ]);
+        // The rewritten branch no longer holds its pushed head, but its saved tip does
+        assert!(!has_pushed_commits("b1", "b1'", None, &is_ancestor).unwrap());
+        assert!(has_pushed_commits("b1", "b1'", Some("b1"), &is_ancestor).unwrap());
+        assert!(has_pushed_commits("c1", "c1", None, &is_ancestor).unwrap());
+        // A saved tip doesn't hide commits missing from it
+        assert!(!has_pushed_commits("b2", "b1'", Some("b1"), &is_ancestor).unwrap());
+
+        // The rebased branch is skipped, while the rest are still rebased
+        assert!(!needs_rebase("a1'", "b1'", Some("b1"), &is_ancestor).unwrap());
+        assert!(needs_rebase("b1'", "c1", None, &is_ancestor).unwrap());
+        // A saved branch whose rebase was aborted is rebased again
+        assert!(needs_rebase("a1'", "b1", Some("b1"), &is_ancestor).unwrap());
+    }
+
+    #[test]
+    fn test_build_stack_chain() {
+        let reqs = vec![
+            make_req(3, "part-3", "part-2", "open"),
+            make_req(1, "part-1", "main", "open"),
+            make_req(9, "unrelated", "main", "open"),
+            make_req(2, "part-2", "part-1", "open"),
+        ];
+        let expected = vec![(1, 0, None), (2, 1, Some(1)), (3, 2, Some(2))];
+        assert_eq!(expected, summarize(&build_stack(&reqs, 2)));
+        assert_eq!(expected, summarize(&build_stack(&reqs, 3)));
+        assert_eq!(vec![(9, 0, None)], summarize(&build_stack(&reqs, 9)));
+        assert!(build_stack(&reqs, 4).is_empty());
+    }
+
+    #[test]
+    fn test_build_stack_tree() {
+        let reqs = vec![
+            make_req(1, "base", "main", "open"),
+            make_req(2, "left", "base", "open"),
+            make_req(3, "right", "base", "open"),
+            make_req(4, "left-top", "left", "open"),
+            make_req(5, "abandoned", "base", "closed"),
+        ];
+        assert_eq!(
+            vec![
+                (1, 0, None),
+                (2, 1, Some(1)),
+                (4, 2, Some(2)),
+                (3, 1, Some(1))
+            ],
+            summarize(&build_stack(&reqs, 4))
+        );
+    }
+
+    #[test]
+    fn test_build_stack_merged_base() {
+        let reqs = vec![
+            make_req(1, "part-1", "main", "merged"),
+            make_req(2, "part-2", "part-1", "open"),
+            // The branch was reused for a later request
+            make_req(6, "part-1", "main", "open"),
+        ];
+        assert_eq!(
+            vec![(6, 0, None), (2, 1, Some(6))],
+            summarize(&build_stack(&reqs, 2))
+        );
+        let reqs = &reqs[..2];
+        assert_eq!(
+            vec![(1, 0, None), (2, 1, Some(1))],
+            summarize(&build_stack(reqs, 2))
+        );
+    }
+
+    #[test]
+    fn test_build_stack_cycle() {
+        let reqs = vec![
+            make_req(1, "first", "second", "open"),
+            make_req(2, "second", "first", "open"),
+        ];
+        assert_eq!(
+            vec![(2, 0, None), (1, 1, Some(2))],
+            summarize(&build_stack(&reqs, 1))
+        );
+    }
+}
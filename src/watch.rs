@@ -0,0 +1,296 @@
+//! Keep the list of open requests up to date in the terminal
+use crate::remotes::{MergeRequest, PipelineStatus, RateLimit, Remote};
+use crate::{get_remote_hard, prepare_requests, print_requests, ListOptions, RemoteRequests};
+use anyhow::Result;
+use colored::*;
+use log::{debug, info, trace, warn};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{stdout, Write};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How a request differs from how it looked in the previous poll
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestChange {
+    New,
+    Updated,
+    PipelineChanged,
+}
+
+impl fmt::Display for RequestChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let change = match self {
+            RequestChange::New => "new",
+            RequestChange::Updated => "updated",
+            RequestChange::PipelineChanged => "ci changed",
+        };
+        write!(f, "{}", change)
+    }
+}
+
+/// The parts of a request that are compared between polls
+#[derive(Debug)]
+struct RequestSnapshot {
+    updated_at: String,
+    pipeline_status: PipelineStatus,
+}
+
+/// Record the state of the requests for comparing against the next poll
+fn take_snapshot(reqs: &[MergeRequest]) -> HashMap<i64, RequestSnapshot> {
+    reqs.iter()
+        .map(|req| {
+            let snapshot = RequestSnapshot {
+                updated_at: req.updated_at.clone(),
+                pipeline_status: req.pipeline_status,
+            };
+            (req.id, snapshot)
+        })
+        .collect()
+}
+
+/// Find the requests that are new or have changed since the snapshot was taken. A new pipeline
+/// status takes precedence over other updates, since pushes change both.
+fn detect_changes(
+    previous: &HashMap<i64, RequestSnapshot>,
+    current: &[MergeRequest],
+) -> HashMap<i64, RequestChange> {
+    current
+        .iter()
+        .filter_map(|req| {
+            let change = match previous.get(&req.id) {
+                None => RequestChange::New,
+                Some(snapshot) if snapshot.pipeline_status != req.pipeline_status => {
+                    RequestChange::PipelineChanged
+                }
+                Some(snapshot) if snapshot.updated_at != req.updated_at => RequestChange::Updated,
+                Some(_) => return None,
+            };
+            Some((req.id, change))
+        })
+        .collect()
+}
+
+/// Work out how many seconds to wait before the next poll. If the next poll could exhaust the
+/// rate limit, wait until the limit resets instead.
+fn get_poll_delay(
+    interval: u64,
+    rate_limit: Option<RateLimit>,
+    requests_per_poll: usize,
+    now: u64,
+) -> u64 {
+    match rate_limit {
+        Some(limit) if limit.remaining < requests_per_poll as u64 && limit.reset > now => {
+            debug!("Rate limit nearly exhausted: {:?}", limit);
+            interval.max(limit.reset - now)
+        }
+        _ => interval,
+    }
+}
+
+/// Query the open requests along with their CI and review details
+fn poll_open_requests(remote: &mut dyn Remote, options: &ListOptions) -> Result<Vec<MergeRequest>> {
+    let mrs = remote.get_req_names()?;
+    Ok(prepare_requests(remote, mrs, options))
+}
+
+/// Repeatedly poll and redraw the open requests until interrupted. A failed poll leaves the
+/// previous requests on screen, and is retried on the next interval.
+pub fn watch_open_requests(remote_name: &str, options: &ListOptions, interval: u64) -> ! {
+    info!("Watching open requests every {}s", interval);
+    let mut remote = get_remote_hard(remote_name, true);
+    let mut previous: Option<HashMap<i64, RequestSnapshot>> = None;
+    let mut mrs = vec![];
+    let mut changes = HashMap::new();
+    loop {
+        let failure = match poll_open_requests(remote.as_mut(), options) {
+            Ok(polled) => {
+                changes = match &previous {
+                    Some(snapshot) => detect_changes(snapshot, &polled),
+                    None => HashMap::new(),
+                };
+                trace!("Changes since the last poll: {:?}", changes);
+                mrs = polled;
+                previous = Some(take_snapshot(&mrs));
+                None
+            }
+            Err(error) => {
+                warn!("There was a problem querying the open reqs: {}", error);
+                Some(error)
+            }
+        };
+        // Clear the screen and move the cursor to the top-left before redrawing
+        print!("\x1b[2J\x1b[H");
+        let source = RemoteRequests {
+            remote_name,
+            remote: remote.as_mut(),
+            mrs: &mrs,
+        };
+        print_requests(&mut [source], options, Some(&changes));
+        if let Some(error) = failure {
+            println!("\n{}", format!("Last refresh failed: {}", error).red());
+        }
+
+        let cache = remote.get_response_cache();
+        let rate_limit = cache.get_rate_limit();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let delay = get_poll_delay(interval, rate_limit, cache.take_request_count(), now);
+        let mut status = format!("Refreshing in {}s. Press Ctrl-C to stop.", delay);
+        if let Some(limit) = rate_limit {
+            status.push_str(&format!(" {} API requests remaining.", limit.remaining));
+        }
+        println!("\n{}", status.dimmed());
+        let _ = stdout().flush();
+        thread::sleep(Duration::from_secs(delay));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remotes::testing::{gitlab_mr_json, make_gitlab_remote, MockResponse, MockServer};
+    use crate::remotes::{DraftFilter, SortOrder};
+
+    fn make_options() -> ListOptions {
+        ListOptions {
+            drafts: DraftFilter::All,
+            sort: None,
+            order: SortOrder::Asc,
+            group_by: None,
+            all_remotes: false,
+            whole_group: false,
+        }
+    }
+
+    fn mr_json(iid: i64, updated_at: &str, pipeline_status: Option<&str>) -> String {
+        gitlab_mr_json(serde_json::json!({
+            "id": iid, "iid": iid, "title": format!("MR {}", iid),
+            "source_branch": format!("branch-{}", iid), "updated_at": updated_at,
+            "head_pipeline": pipeline_status.map(|status| serde_json::json!({"id": 1, "status": status})),
+        }))
+        .to_string()
+    }
+
+    fn make_req(id: i64) -> MergeRequest {
+        MergeRequest {
+            id,
+            title: format!("MR {}", id),
+            updated_at: String::from("2020-01-01T00:00:00Z"),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_detect_changes() {
+        let mut reqs = vec![make_req(1), make_req(2), make_req(3)];
+        let snapshot = take_snapshot(&reqs);
+        reqs[0].updated_at = String::from("2020-01-02T00:00:00Z");
+        reqs[1].pipeline_status = PipelineStatus::Failed;
+        reqs.push(make_req(4));
+        let changes = detect_changes(&snapshot, &reqs);
+        assert_eq!(
+            HashMap::from([
+                (1, RequestChange::Updated),
+                (2, RequestChange::PipelineChanged),
+                (4, RequestChange::New),
+            ]),
+            changes
+        );
+    }
+
+    #[test]
+    fn test_get_poll_delay_plenty_remaining() {
+        let limit = RateLimit {
+            remaining: 100,
+            reset: 1_000,
+        };
+        assert_eq!(60, get_poll_delay(60, Some(limit), 10, 500));
+    }
+
+    #[test]
+    fn test_get_poll_delay_waits_for_reset() {
+        let limit = RateLimit {
+            remaining: 5,
+            reset: 1_000,
+        };
+        assert_eq!(500, get_poll_delay(60, Some(limit), 10, 500));
+    }
+
+    #[test]
+    fn test_poll_open_requests_changes_between_polls() {
+        let server = MockServer::start();
+        let list_path = "/projects/1/merge_requests";
+        server.respond(
+            "GET",
+            list_path,
+            MockResponse::json(&format!(
+                "[{}]",
+                mr_json(5, "2020-01-01T00:00:00.000Z", None)
+            ))
+            .with_header("ETag", "\"v1\""),
+        );
+        server.respond("GET", list_path, MockResponse::status(304));
+        server.respond(
+            "GET",
+            list_path,
+            MockResponse::json(&format!(
+                "[{}, {}]",
+                mr_json(6, "2020-01-03T00:00:00.000Z", None),
+                mr_json(5, "2020-01-01T00:00:00.000Z", None)
+            )),
+        );
+        let mr_5 = "/projects/1/merge_requests/5";
+        server.respond(
+            "GET",
+            mr_5,
+            MockResponse::json(&mr_json(5, "2020-01-01T00:00:00.000Z", Some("running"))),
+        );
+        server.respond(
+            "GET",
+            mr_5,
+            MockResponse::json(&mr_json(5, "2020-01-01T00:00:00.000Z", Some("success"))),
+        );
+        server.respond(
+            "GET",
+            "/projects/1/merge_requests/6",
+            MockResponse::json(&mr_json(6, "2020-01-03T00:00:00.000Z", Some("success"))),
+        );
+        for approvals in [
+            "/projects/1/merge_requests/5/approvals",
+            "/projects/1/merge_requests/6/approvals",
+        ] {
+            server.respond(
+                "GET",
+                approvals,
+                MockResponse::json(r#"{"approved": false, "approved_by": []}"#),
+            );
+        }
+        server.respond(
+            "GET",
+            "/user",
+            MockResponse::json(r#"{"id": 1, "username": "aru"}"#),
+        );
+        let mut remote = make_gitlab_remote(&server);
+        let options = make_options();
+
+        let first = poll_open_requests(&mut remote, &options).unwrap();
+        assert_eq!(vec![5], first.iter().map(|req| req.id).collect::<Vec<_>>());
+        assert_eq!(PipelineStatus::Running, first[0].pipeline_status);
+
+        let second = poll_open_requests(&mut remote, &options).unwrap();
+        let list_requests = server.requests_to(list_path);
+        assert_eq!(None, list_requests[0].header("If-None-Match"));
+        assert_eq!(Some("\"v1\""), list_requests[1].header("If-None-Match"));
+        let changes = detect_changes(&take_snapshot(&first), &second);
+        assert_eq!(
+            HashMap::from([(5, RequestChange::PipelineChanged)]),
+            changes
+        );
+
+        let third = poll_open_requests(&mut remote, &options).unwrap();
+        let changes = detect_changes(&take_snapshot(&second), &third);
+        assert_eq!(HashMap::from([(6, RequestChange::New)]), changes);
+    }
+}